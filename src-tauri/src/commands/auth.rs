@@ -1,9 +1,31 @@
+use crate::commands::storage::get_settings;
 use crate::config;
-use crate::utils::keychain::{self, keys};
+use crate::error::GopenerError;
+use crate::utils::keychain::{self, keys, KeychainError};
+use crate::utils::loopback;
+use crate::utils::retry::RetryPolicy;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Emitter;
+use thiserror::Error;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+/// How long the loopback listener waits for the browser to come back
+const AUTH_CALLBACK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How many completed sign-in states to remember for replay detection
+const MAX_CONSUMED_STATES: usize = 16;
+
+// Cancellation handle for the sign-in flow currently waiting on the loopback listener
+static PENDING_SIGN_IN: Mutex<Option<oneshot::Sender<()>>> = Mutex::new(None);
+
+// States of recently redeemed flows, so a replayed code can be told apart from a forged one
+static CONSUMED_STATES: Mutex<Vec<String>> = Mutex::new(Vec::new());
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenResponse {
@@ -13,13 +35,54 @@ pub struct TokenResponse {
     pub token_type: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AuthState {
     pub is_authenticated: bool,
     pub access_token: Option<String>,
     pub expires_at: Option<u64>,
 }
 
+/// PKCE verifier and CSRF state of a sign-in flow that has not been redeemed yet
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct PendingAuthorization {
+    code_verifier: String,
+    state: String,
+    redirect_uri: String,
+}
+
+/// Payload of the `auth-completed` event emitted when the loopback flow ends
+#[derive(Debug, Serialize, Clone)]
+pub struct AuthCompleted {
+    pub success: bool,
+    pub cancelled: bool,
+    pub auth_state: Option<AuthState>,
+    pub error: Option<GopenerError>,
+}
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("Sign-in was cancelled")]
+    Cancelled,
+    #[error("Timed out waiting for the browser to complete sign-in")]
+    TimedOut,
+    #[error("Authorization was denied: {0}")]
+    Denied(String),
+    #[error("Authorization callback did not include a code")]
+    MissingCode,
+    #[error("Sign-in state does not match the pending request; please sign in again")]
+    StateMismatch,
+    #[error("This authorization code has already been used; please sign in again")]
+    AuthorizationReused,
+    #[error("No sign-in is in progress; please sign in again")]
+    NoPendingAuthorization,
+    #[error("Callback listener failed: {0}")]
+    Listener(#[from] std::io::Error),
+    #[error(transparent)]
+    Keychain(#[from] KeychainError),
+    #[error(transparent)]
+    TokenExchange(GopenerError),
+}
+
 /// Generate PKCE code verifier and challenge
 fn generate_pkce() -> (String, String) {
     // Generate random 32 bytes for code verifier
@@ -36,6 +99,50 @@ fn generate_pkce() -> (String, String) {
     (code_verifier, code_challenge)
 }
 
+/// Generate a random value for the OAuth `state` parameter
+fn generate_state() -> String {
+    let mut rng = rand::thread_rng();
+    let random_bytes: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
+    URL_SAFE_NO_PAD.encode(&random_bytes)
+}
+
+/// Compare two secrets without short-circuiting on the first differing byte
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a
+            .bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// Check the `state` returned with an authorization code against the pending flow
+fn validate_state(
+    pending: Option<PendingAuthorization>,
+    received_state: &str,
+    consumed_states: &[String],
+) -> Result<PendingAuthorization, AuthError> {
+    let was_consumed = consumed_states
+        .iter()
+        .any(|consumed| constant_time_eq(consumed, received_state));
+
+    match pending {
+        Some(pending) if constant_time_eq(&pending.state, received_state) => Ok(pending),
+        _ if was_consumed => Err(AuthError::AuthorizationReused),
+        Some(_) => Err(AuthError::StateMismatch),
+        None => Err(AuthError::NoPendingAuthorization),
+    }
+}
+
+/// Remember a redeemed state so later attempts with it are reported as replays
+fn mark_state_consumed(state: &str) {
+    let mut consumed = CONSUMED_STATES.lock().unwrap();
+    consumed.push(state.to_string());
+    if consumed.len() > MAX_CONSUMED_STATES {
+        consumed.remove(0);
+    }
+}
+
 /// Get OAuth client ID (custom keychain override, or compile-time default)
 async fn get_client_id() -> String {
     match keychain::retrieve(keys::CUSTOM_CLIENT_ID) {
@@ -52,23 +159,20 @@ async fn get_client_secret() -> Option<String> {
     }
 }
 
-/// Generate the OAuth authorization URL
-#[tauri::command]
-pub async fn get_auth_url() -> Result<String, String> {
-    let (code_verifier, code_challenge) = generate_pkce();
-
-    // Store the code verifier for later use
-    keychain::store(keys::PKCE_VERIFIER, &code_verifier).map_err(|e| e.to_string())?;
-
-    let client_id = get_client_id().await;
-
+/// Build the authorization URL for the given redirect URI, PKCE challenge and state
+fn build_auth_url(
+    client_id: &str,
+    redirect_uri: &str,
+    code_challenge: &str,
+    state: &str,
+) -> String {
     let scopes = [
         "https://www.googleapis.com/auth/drive.file",
         "https://www.googleapis.com/auth/drive.readonly",
     ]
     .join(" ");
 
-    let auth_url = format!(
+    format!(
         "{}?\
         client_id={}&\
         redirect_uri={}&\
@@ -76,59 +180,195 @@ pub async fn get_auth_url() -> Result<String, String> {
         scope={}&\
         code_challenge={}&\
         code_challenge_method=S256&\
+        state={}&\
         access_type=offline&\
         prompt=consent",
         config::GOOGLE_AUTH_ENDPOINT,
-        urlencoding::encode(&client_id),
-        urlencoding::encode(config::OAUTH_REDIRECT_URI),
+        urlencoding::encode(client_id),
+        urlencoding::encode(redirect_uri),
         urlencoding::encode(&scopes),
-        urlencoding::encode(&code_challenge)
-    );
+        urlencoding::encode(code_challenge),
+        urlencoding::encode(state)
+    )
+}
+
+/// Start a new flow: create the PKCE pair and state, remember them and return the authorization URL
+async fn prepare_auth_url(redirect_uri: &str) -> Result<String, GopenerError> {
+    let (code_verifier, code_challenge) = generate_pkce();
+    let state = generate_state();
+
+    // Store the verifier and state together; both are needed to redeem the code
+    let pending = PendingAuthorization {
+        code_verifier,
+        state: state.clone(),
+        redirect_uri: redirect_uri.to_string(),
+    };
+    keychain::store_json(keys::PENDING_AUTHORIZATION, &pending)?;
+
+    let client_id = get_client_id().await;
+
+    Ok(build_auth_url(&client_id, redirect_uri, &code_challenge, &state))
+}
+
+/// Generate the OAuth authorization URL
+#[tauri::command]
+pub async fn get_auth_url() -> Result<String, GopenerError> {
+    prepare_auth_url(config::OAUTH_REDIRECT_URI).await
+}
+
+/// Start the loopback sign-in flow and return the URL to open in the browser.
+///
+/// A local listener on the redirect port waits for Google's callback, exchanges
+/// the code itself and emits `auth-completed` with the outcome.
+#[tauri::command]
+pub async fn start_sign_in(app: tauri::AppHandle) -> Result<String, GopenerError> {
+    let (listener, redirect_uri) = loopback::bind(config::OAUTH_REDIRECT_URI)
+        .await
+        .map_err(|e| GopenerError::Io(format!("Failed to start callback listener: {}", e)))?;
+
+    let auth_url = prepare_auth_url(&redirect_uri).await?;
+
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    if let Some(previous) = PENDING_SIGN_IN.lock().unwrap().replace(cancel_tx) {
+        // Only one flow can own the browser redirect at a time
+        let _ = previous.send(());
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let event = match wait_for_code(&listener, AUTH_CALLBACK_TIMEOUT, cancel_rx).await {
+            Ok((code, state)) => match redeem_code(code, &state).await {
+                Ok(state) => AuthCompleted {
+                    success: true,
+                    cancelled: false,
+                    auth_state: Some(state),
+                    error: None,
+                },
+                Err(e) => AuthCompleted {
+                    success: false,
+                    cancelled: false,
+                    auth_state: None,
+                    error: Some(e.into()),
+                },
+            },
+            Err(e) => AuthCompleted {
+                success: false,
+                cancelled: matches!(e, AuthError::Cancelled),
+                auth_state: None,
+                error: Some(e.into()),
+            },
+        };
+
+        // Drop our cancellation handle unless a newer flow has replaced it
+        let mut pending = PENDING_SIGN_IN.lock().unwrap();
+        if pending.as_ref().is_some_and(|tx| tx.is_closed()) {
+            pending.take();
+        }
+        drop(pending);
+
+        let _ = app.emit("auth-completed", event);
+    });
 
     Ok(auth_url)
 }
 
-/// Exchange authorization code for tokens
+/// Cancel a sign-in flow that is waiting for the browser callback
 #[tauri::command]
-pub async fn exchange_code(code: String) -> Result<AuthState, String> {
-    let client_id = get_client_id().await;
-    let client_secret = get_client_secret().await;
+pub async fn cancel_sign_in() -> Result<(), GopenerError> {
+    if let Some(cancel) = PENDING_SIGN_IN.lock().unwrap().take() {
+        let _ = cancel.send(());
+    }
+    Ok(())
+}
+
+/// Wait for the browser to hit the loopback listener and return the authorization code and state
+async fn wait_for_code(
+    listener: &TcpListener,
+    timeout: Duration,
+    cancel: oneshot::Receiver<()>,
+) -> Result<(String, String), AuthError> {
+    let params = tokio::select! {
+        result = tokio::time::timeout(timeout, loopback::wait_for_callback(listener)) => {
+            result.map_err(|_| AuthError::TimedOut)??
+        }
+        _ = cancel => return Err(AuthError::Cancelled),
+    };
 
-    // Retrieve the stored code verifier
-    let code_verifier = keychain::retrieve(keys::PKCE_VERIFIER)
-        .map_err(|e| e.to_string())?
-        .ok_or("No PKCE verifier found")?;
+    if let Some(error) = params.error {
+        return Err(AuthError::Denied(error));
+    }
+
+    let code = params.code.ok_or(AuthError::MissingCode)?;
+    let state = params.state.ok_or(AuthError::StateMismatch)?;
+    Ok((code, state))
+}
 
+/// POST an authorization-code grant to the token endpoint.
+///
+/// A code can be redeemed only once, so the request is not repeated once Google
+/// may have seen it.
+async fn request_token_exchange(
+    token_endpoint: &str,
+    params: &[(&str, String)],
+    retry: &RetryPolicy,
+) -> Result<TokenResponse, GopenerError> {
     let client = reqwest::Client::new();
 
+    let response = retry
+        .non_idempotent()
+        .send(|| client.post(token_endpoint).form(params))
+        .await
+        .map_err(|e| GopenerError::Network(format!("Failed to exchange code: {}", e)))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(GopenerError::google("Token exchange failed", status, &error_text));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| GopenerError::Internal(format!("Failed to parse token response: {}", e)))
+}
+
+/// Exchange authorization code for tokens
+#[tauri::command]
+pub async fn exchange_code(code: String, state: String) -> Result<AuthState, GopenerError> {
+    Ok(redeem_code(code, &state).await?)
+}
+
+/// Validate the state of an authorization code and exchange it for tokens
+async fn redeem_code(code: String, state: &str) -> Result<AuthState, AuthError> {
+    let pending = keychain::retrieve_json(keys::PENDING_AUTHORIZATION)?;
+    let pending = validate_state(pending, state, &CONSUMED_STATES.lock().unwrap())?;
+
+    // Consume the flow before talking to Google so a code can only be redeemed once
+    let _ = keychain::delete(keys::PENDING_AUTHORIZATION);
+    mark_state_consumed(state);
+
+    let client_id = get_client_id().await;
+    let client_secret = get_client_secret().await;
+
     let mut params = vec![
         ("code", code),
         ("client_id", client_id),
-        ("redirect_uri", config::OAUTH_REDIRECT_URI.to_string()),
+        ("redirect_uri", pending.redirect_uri),
         ("grant_type", "authorization_code".to_string()),
-        ("code_verifier", code_verifier),
+        ("code_verifier", pending.code_verifier),
     ];
 
     if let Some(secret) = client_secret {
         params.push(("client_secret", secret));
     }
 
-    let response = client
-        .post(config::GOOGLE_TOKEN_ENDPOINT)
-        .form(&params)
-        .send()
+    // Unreadable settings shouldn't stand in the way of signing in
+    let retry = get_settings()
         .await
-        .map_err(|e| format!("Failed to exchange code: {}", e))?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("Token exchange failed: {}", error_text));
-    }
-
-    let token_response: TokenResponse = response
-        .json()
+        .map(|settings| settings.retry_policy())
+        .unwrap_or_default();
+    let token_response = request_token_exchange(config::GOOGLE_TOKEN_ENDPOINT, &params, &retry)
         .await
-        .map_err(|e| format!("Failed to parse token response: {}", e))?;
+        .map_err(AuthError::TokenExchange)?;
 
     // Calculate expiry timestamp
     let expires_at = std::time::SystemTime::now()
@@ -138,16 +378,13 @@ pub async fn exchange_code(code: String) -> Result<AuthState, String> {
         + token_response.expires_in;
 
     // Store tokens securely
-    keychain::store(keys::ACCESS_TOKEN, &token_response.access_token).map_err(|e| e.to_string())?;
+    keychain::store(keys::ACCESS_TOKEN, &token_response.access_token)?;
 
     if let Some(ref refresh_token) = token_response.refresh_token {
-        keychain::store(keys::REFRESH_TOKEN, refresh_token).map_err(|e| e.to_string())?;
+        keychain::store(keys::REFRESH_TOKEN, refresh_token)?;
     }
 
-    keychain::store(keys::TOKEN_EXPIRY, &expires_at.to_string()).map_err(|e| e.to_string())?;
-
-    // Clean up PKCE verifier
-    let _ = keychain::delete(keys::PKCE_VERIFIER);
+    keychain::store(keys::TOKEN_EXPIRY, &expires_at.to_string())?;
 
     Ok(AuthState {
         is_authenticated: true,
@@ -158,13 +395,12 @@ pub async fn exchange_code(code: String) -> Result<AuthState, String> {
 
 /// Refresh the access token using the refresh token
 #[tauri::command]
-pub async fn refresh_token() -> Result<AuthState, String> {
+pub async fn refresh_token() -> Result<AuthState, GopenerError> {
     let client_id = get_client_id().await;
     let client_secret = get_client_secret().await;
 
-    let refresh_token = keychain::retrieve(keys::REFRESH_TOKEN)
-        .map_err(|e| e.to_string())?
-        .ok_or("No refresh token found")?;
+    let refresh_token =
+        keychain::retrieve(keys::REFRESH_TOKEN)?.ok_or(GopenerError::NotAuthenticated)?;
 
     let client = reqwest::Client::new();
 
@@ -178,22 +414,25 @@ pub async fn refresh_token() -> Result<AuthState, String> {
         params.push(("client_secret", secret));
     }
 
-    let response = client
-        .post(config::GOOGLE_TOKEN_ENDPOINT)
-        .form(&params)
-        .send()
+    let retry = get_settings()
+        .await
+        .map(|settings| settings.retry_policy())
+        .unwrap_or_default();
+    let response = retry
+        .send(|| client.post(config::GOOGLE_TOKEN_ENDPOINT).form(&params))
         .await
-        .map_err(|e| format!("Failed to refresh token: {}", e))?;
+        .map_err(|e| GopenerError::Network(format!("Failed to refresh token: {}", e)))?;
 
-    if !response.status().is_success() {
+    let status = response.status();
+    if !status.is_success() {
         let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("Token refresh failed: {}", error_text));
+        return Err(GopenerError::google("Token refresh failed", status, &error_text));
     }
 
     let token_response: TokenResponse = response
         .json()
         .await
-        .map_err(|e| format!("Failed to parse token response: {}", e))?;
+        .map_err(|e| GopenerError::Internal(format!("Failed to parse token response: {}", e)))?;
 
     let expires_at = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -201,8 +440,8 @@ pub async fn refresh_token() -> Result<AuthState, String> {
         .as_secs()
         + token_response.expires_in;
 
-    keychain::store(keys::ACCESS_TOKEN, &token_response.access_token).map_err(|e| e.to_string())?;
-    keychain::store(keys::TOKEN_EXPIRY, &expires_at.to_string()).map_err(|e| e.to_string())?;
+    keychain::store(keys::ACCESS_TOKEN, &token_response.access_token)?;
+    keychain::store(keys::TOKEN_EXPIRY, &expires_at.to_string())?;
 
     Ok(AuthState {
         is_authenticated: true,
@@ -213,21 +452,20 @@ pub async fn refresh_token() -> Result<AuthState, String> {
 
 /// Sign out and clear all stored credentials
 #[tauri::command]
-pub async fn sign_out() -> Result<(), String> {
+pub async fn sign_out() -> Result<(), GopenerError> {
     let _ = keychain::delete(keys::ACCESS_TOKEN);
     let _ = keychain::delete(keys::REFRESH_TOKEN);
     let _ = keychain::delete(keys::TOKEN_EXPIRY);
-    let _ = keychain::delete(keys::PKCE_VERIFIER);
+    let _ = keychain::delete(keys::PENDING_AUTHORIZATION);
     Ok(())
 }
 
 /// Check current authentication state
 #[tauri::command]
-pub async fn check_auth() -> Result<AuthState, String> {
-    let access_token = keychain::retrieve(keys::ACCESS_TOKEN).map_err(|e| e.to_string())?;
+pub async fn check_auth() -> Result<AuthState, GopenerError> {
+    let access_token = keychain::retrieve(keys::ACCESS_TOKEN)?;
 
-    let expires_at = keychain::retrieve(keys::TOKEN_EXPIRY)
-        .map_err(|e| e.to_string())?
+    let expires_at = keychain::retrieve(keys::TOKEN_EXPIRY)?
         .and_then(|s| s.parse::<u64>().ok());
 
     // Check if token exists and is not expired
@@ -269,12 +507,10 @@ pub async fn check_auth() -> Result<AuthState, String> {
 }
 
 /// Get a valid access token, refreshing if necessary
-pub async fn get_valid_token() -> Result<String, String> {
+pub async fn get_valid_token() -> Result<String, GopenerError> {
     let state = check_auth().await?;
 
-    state
-        .access_token
-        .ok_or_else(|| "Not authenticated".to_string())
+    state.access_token.ok_or(GopenerError::NotAuthenticated)
 }
 
 #[cfg(test)]
@@ -373,4 +609,248 @@ mod tests {
     fn test_redirect_uri_is_localhost() {
         assert!(config::OAUTH_REDIRECT_URI.starts_with("http://localhost"));
     }
+
+    #[test]
+    fn test_build_auth_url_uses_given_redirect_uri() {
+        let url = build_auth_url("client", "http://localhost:49152", "challenge", "st4te");
+        assert!(url.starts_with(config::GOOGLE_AUTH_ENDPOINT));
+        assert!(url.contains("redirect_uri=http%3A%2F%2Flocalhost%3A49152"));
+        assert!(url.contains("code_challenge=challenge"));
+        assert!(url.contains("&state=st4te&"));
+    }
+
+    #[test]
+    fn test_generate_state_is_random_and_url_safe() {
+        let s1 = generate_state();
+        let s2 = generate_state();
+
+        assert_ne!(s1, s2);
+        assert_eq!(s1.len(), 43);
+        assert!(s1
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    }
+
+    fn pending(state: &str) -> PendingAuthorization {
+        PendingAuthorization {
+            code_verifier: "verifier".to_string(),
+            state: state.to_string(),
+            redirect_uri: "http://localhost:8085".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate_state_accepts_matching_state() {
+        let result = validate_state(Some(pending("abc")), "abc", &[]);
+        assert_eq!(result.unwrap(), pending("abc"));
+    }
+
+    #[test]
+    fn test_validate_state_rejects_mismatch() {
+        let result = validate_state(Some(pending("abc")), "forged", &[]);
+        assert!(matches!(result, Err(AuthError::StateMismatch)));
+
+        let result = validate_state(Some(pending("abc")), "ab", &[]);
+        assert!(matches!(result, Err(AuthError::StateMismatch)));
+    }
+
+    #[test]
+    fn test_validate_state_detects_replayed_code() {
+        let consumed = vec!["used".to_string()];
+
+        let result = validate_state(None, "used", &consumed);
+        assert!(matches!(result, Err(AuthError::AuthorizationReused)));
+
+        // A newer flow is pending, but the code belongs to an old one
+        let result = validate_state(Some(pending("fresh")), "used", &consumed);
+        assert!(matches!(result, Err(AuthError::AuthorizationReused)));
+    }
+
+    #[test]
+    fn test_validate_state_without_pending_flow() {
+        let result = validate_state(None, "abc", &[]);
+        assert!(matches!(result, Err(AuthError::NoPendingAuthorization)));
+    }
+
+    #[test]
+    fn test_pending_authorization_roundtrip() {
+        let json = serde_json::to_string(&pending("abc")).unwrap();
+        let deserialized: PendingAuthorization = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, pending("abc"));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("same", "same"));
+        assert!(!constant_time_eq("same", "sane"));
+        assert!(!constant_time_eq("same", "same-but-longer"));
+        assert!(constant_time_eq("", ""));
+    }
+
+    #[tokio::test]
+    async fn test_loopback_flow_exchanges_code_with_token_endpoint() {
+        use crate::utils::test_server::{ScriptedResponse, TestServer};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let token_server = TestServer::start(vec![ScriptedResponse::json(
+            200,
+            serde_json::json!({
+                "access_token": "ya29.loopback",
+                "refresh_token": "1//loopback",
+                "expires_in": 3600,
+                "token_type": "Bearer"
+            }),
+        )])
+        .await;
+
+        let (listener, redirect_uri) = loopback::bind("http://localhost:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_cancel_tx, cancel_rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET /?code=4%2Fauth-code&state=s1 HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+        });
+
+        let (code, state) = wait_for_code(&listener, Duration::from_secs(5), cancel_rx)
+            .await
+            .unwrap();
+        assert_eq!(code, "4/auth-code");
+        assert_eq!(state, "s1");
+
+        let params = vec![
+            ("code", code),
+            ("redirect_uri", redirect_uri.clone()),
+            ("grant_type", "authorization_code".to_string()),
+            ("code_verifier", "verifier".to_string()),
+        ];
+        let tokens = request_token_exchange(
+            &format!("{}/token", token_server.url),
+            &params,
+            &RetryPolicy::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tokens.access_token, "ya29.loopback");
+
+        let requests = token_server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/token");
+        assert_eq!(
+            requests[0].header("content-type"),
+            Some("application/x-www-form-urlencoded")
+        );
+        let body = requests[0].body_text();
+        assert!(body.contains("code=4%2Fauth-code"));
+        assert!(body.contains("code_verifier=verifier"));
+        assert!(body.contains(&format!("redirect_uri={}", urlencoding::encode(&redirect_uri))));
+    }
+
+    #[tokio::test]
+    async fn test_token_exchange_reports_endpoint_error() {
+        use crate::utils::test_server::{ScriptedResponse, TestServer};
+
+        let token_server =
+            TestServer::start(vec![ScriptedResponse::new(400, "{\"error\":\"invalid_grant\"}")]).await;
+
+        let result = request_token_exchange(
+            &token_server.url,
+            &[("code", "bad".to_string())],
+            &RetryPolicy::default(),
+        )
+        .await;
+        let err = result.unwrap_err();
+        assert!(err.to_string().starts_with("Token exchange failed"));
+        assert_eq!(err.reason(), Some("invalid_grant"));
+        assert_eq!(err.code(), "not_authenticated");
+    }
+
+    #[tokio::test]
+    async fn test_token_exchange_is_not_repeated_after_server_error() {
+        use crate::utils::test_server::{ScriptedResponse, TestServer};
+
+        let token_server = TestServer::start(vec![
+            ScriptedResponse::new(500, "backend error"),
+            ScriptedResponse::new(200, "{\"access_token\":\"ya29.twice\"}"),
+        ])
+        .await;
+
+        let result = request_token_exchange(
+            &token_server.url,
+            &[("code", "4/once".to_string())],
+            &RetryPolicy::default(),
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err().code(), "google_unavailable");
+        assert_eq!(token_server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_code_requires_state() {
+        use tokio::io::AsyncWriteExt;
+
+        let (listener, _) = loopback::bind("http://localhost:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_cancel_tx, cancel_rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET /?code=abc HTTP/1.1\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let result = wait_for_code(&listener, Duration::from_secs(5), cancel_rx).await;
+        assert!(matches!(result, Err(AuthError::StateMismatch)));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_code_times_out() {
+        let (listener, _) = loopback::bind("http://localhost:0").await.unwrap();
+        let (_cancel_tx, cancel_rx) = oneshot::channel();
+
+        let result = wait_for_code(&listener, Duration::from_millis(50), cancel_rx).await;
+        assert!(matches!(result, Err(AuthError::TimedOut)));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_code_can_be_cancelled() {
+        let (listener, _) = loopback::bind("http://localhost:0").await.unwrap();
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        cancel_tx.send(()).unwrap();
+
+        let result = wait_for_code(&listener, Duration::from_secs(5), cancel_rx).await;
+        assert!(matches!(result, Err(AuthError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_code_reports_denied_consent() {
+        use tokio::io::AsyncWriteExt;
+
+        let (listener, _) = loopback::bind("http://localhost:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_cancel_tx, cancel_rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET /?error=access_denied HTTP/1.1\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let result = wait_for_code(&listener, Duration::from_secs(5), cancel_rx).await;
+        match result {
+            Err(AuthError::Denied(reason)) => assert_eq!(reason, "access_denied"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
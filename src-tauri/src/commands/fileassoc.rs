@@ -1,9 +1,13 @@
+use crate::error::GopenerError;
+#[cfg(any(target_os = "linux", test))]
+use crate::utils::formats;
+
 /// Register file associations for the application
 /// Note: Most file associations are handled via tauri.conf.json bundle settings
 /// This command provides runtime registration for platforms that support it
 
 #[tauri::command]
-pub async fn register_file_associations() -> Result<String, String> {
+pub async fn register_file_associations() -> Result<String, GopenerError> {
     #[cfg(target_os = "windows")]
     {
         register_windows_associations()
@@ -22,12 +26,14 @@ pub async fn register_file_associations() -> Result<String, String> {
 
     #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
     {
-        Err("Unsupported platform".to_string())
+        Err(GopenerError::UnsupportedPlatform(
+            "Unsupported platform".to_string(),
+        ))
     }
 }
 
 #[cfg(target_os = "windows")]
-fn register_windows_associations() -> Result<String, String> {
+fn register_windows_associations() -> Result<String, GopenerError> {
     // On Windows, file associations are typically set during installation
     // via the installer (NSIS/WiX) or can be set via registry
     // For now, we rely on the Tauri bundle configuration
@@ -35,34 +41,27 @@ fn register_windows_associations() -> Result<String, String> {
 }
 
 #[cfg(target_os = "linux")]
-fn register_linux_associations() -> Result<String, String> {
+fn register_linux_associations() -> Result<String, GopenerError> {
     use std::fs;
     use std::path::PathBuf;
 
     // Create .desktop file for Linux
-    let desktop_entry = r#"[Desktop Entry]
-Name=Gopener
-Comment=Upload Office files to Google Drive
-Exec=gopener %F
-Icon=gopener
-Type=Application
-Categories=Office;Utility;
-MimeType=application/msword;application/vnd.openxmlformats-officedocument.wordprocessingml.document;application/vnd.oasis.opendocument.text;application/rtf;text/plain;application/vnd.ms-excel;application/vnd.openxmlformats-officedocument.spreadsheetml.sheet;application/vnd.oasis.opendocument.spreadsheet;text/csv;text/tab-separated-values;application/vnd.ms-powerpoint;application/vnd.openxmlformats-officedocument.presentationml.presentation;application/vnd.oasis.opendocument.presentation;
-"#;
+    let desktop_entry = desktop_entry();
 
     // Get applications directory
-    let home = std::env::var("HOME").map_err(|_| "HOME not set")?;
+    let home = std::env::var("HOME").map_err(|_| GopenerError::Io("HOME not set".to_string()))?;
     let apps_dir = PathBuf::from(&home).join(".local/share/applications");
 
     // Create directory if needed
     if !apps_dir.exists() {
-        fs::create_dir_all(&apps_dir).map_err(|e| format!("Failed to create apps dir: {}", e))?;
+        fs::create_dir_all(&apps_dir)
+            .map_err(|e| GopenerError::Io(format!("Failed to create apps dir: {}", e)))?;
     }
 
     // Write desktop file
     let desktop_path = apps_dir.join("gopener.desktop");
     fs::write(&desktop_path, desktop_entry)
-        .map_err(|e| format!("Failed to write desktop file: {}", e))?;
+        .map_err(|e| GopenerError::Io(format!("Failed to write desktop file: {}", e)))?;
 
     // Update desktop database
     let _ = std::process::Command::new("update-desktop-database")
@@ -74,3 +73,48 @@ MimeType=application/msword;application/vnd.openxmlformats-officedocument.wordpr
         desktop_path.display()
     ))
 }
+
+/// Linux desktop entry announcing every supported format's MIME type
+#[cfg(any(target_os = "linux", test))]
+fn desktop_entry() -> String {
+    // shared-mime-info spells its types in lowercase, e.g. `macroenabled`
+    let mime_types: String = formats::mime_types()
+        .iter()
+        .map(|mime_type| format!("{};", mime_type.to_lowercase()))
+        .collect();
+
+    format!(
+        "[Desktop Entry]
+Name=Gopener
+Comment=Upload Office files to Google Drive
+Exec=gopener %F
+Icon=gopener
+Type=Application
+Categories=Office;Utility;
+MimeType={}
+",
+        mime_types
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_desktop_entry_lists_every_format() {
+        let entry = desktop_entry();
+        let line = entry
+            .lines()
+            .find_map(|line| line.strip_prefix("MimeType="))
+            .unwrap();
+        let listed: Vec<&str> = line.split(';').filter(|m| !m.is_empty()).collect();
+
+        for format in formats::FORMATS {
+            let mime_type = format.mime_type.to_lowercase();
+            assert!(listed.contains(&mime_type.as_str()), "{}", mime_type);
+        }
+        assert!(listed.contains(&"application/vnd.ms-excel.sheet.macroenabled.12"));
+        assert_eq!(listed.len(), formats::mime_types().len());
+    }
+}
@@ -0,0 +1,574 @@
+//! Upload a local directory tree, mirroring its folders in Drive.
+
+use crate::commands::jobs::{JobState, UploadManager};
+use crate::commands::storage::{get_settings, Settings};
+use crate::commands::upload::{
+    resolve_ocr_language, run_batch, BatchItem, ExistingFileMode, FileUploadOutcome, UploadOptions,
+};
+use crate::error::GopenerError;
+use crate::google::drive;
+use crate::utils::file::detect_file_type;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// What to do with files Drive cannot convert
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsupportedFiles {
+    #[default]
+    Skip,
+    UploadUnconverted,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct FolderUploadOptions {
+    pub unsupported: UnsupportedFiles,
+    /// Maximum number of files uploading at once
+    pub concurrency: Option<usize>,
+    /// Language of the text in PDFs and images; defaults to the one in Settings
+    pub ocr_language: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Converted,
+    Unconverted,
+    Skipped,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileSummary {
+    pub name: String,
+    pub local_path: String,
+    pub status: FileStatus,
+    pub job_id: Option<String>,
+    pub file_id: Option<String>,
+    pub web_view_link: Option<String>,
+    pub error: Option<GopenerError>,
+}
+
+/// Drive folder created for a local directory, with what ended up inside it
+#[derive(Debug, Serialize, Clone)]
+pub struct FolderSummary {
+    pub name: String,
+    pub local_path: String,
+    /// `None` when the folder, or one above it, could not be created
+    pub folder_id: Option<String>,
+    /// Why the folder was not created; its files are marked failed
+    pub error: Option<GopenerError>,
+    pub files: Vec<FileSummary>,
+    pub folders: Vec<FolderSummary>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FolderUploadResult {
+    pub batch_id: String,
+    pub root: FolderSummary,
+    pub uploaded: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// Local directory as found on disk, entries sorted by name
+#[derive(Debug, Clone)]
+pub struct LocalFolder {
+    pub name: String,
+    pub path: PathBuf,
+    pub files: Vec<PathBuf>,
+    pub folders: Vec<LocalFolder>,
+}
+
+/// Upload a local directory into Drive, recreating its folder hierarchy
+#[tauri::command]
+pub async fn upload_folder(
+    app: tauri::AppHandle,
+    manager: State<'_, UploadManager>,
+    dir_path: String,
+    parent_id: Option<String>,
+    options: Option<FolderUploadOptions>,
+) -> Result<FolderUploadResult, GopenerError> {
+    let options = options.unwrap_or_default();
+    let settings = get_settings().await?;
+    let ocr_language = resolve_ocr_language(options.ocr_language.clone(), &settings)?;
+    let local = scan_directory(Path::new(&dir_path))
+        .map_err(|e| GopenerError::Io(format!("Failed to read folder: {}", e)))?;
+
+    // Folders first, parents before children, so every file has somewhere to go
+    let mut flat = Vec::new();
+    flatten(&local, None, &mut flat);
+
+    // A folder that can't be created takes its subtree with it, but the rest
+    // still goes up and the summary shows what happened
+    let mut folder_ids: Vec<Result<String, GopenerError>> = Vec::with_capacity(flat.len());
+    for (parent, folder) in &flat {
+        let parent_id = match parent {
+            Some(index) => folder_ids[*index].clone().map(Some),
+            None => Ok(parent_id.clone()),
+        };
+        let created = match parent_id {
+            Ok(parent_id) => drive::create_folder(folder.name.clone(), parent_id)
+                .await
+                .map(|created| created.id),
+            Err(e) => Err(e),
+        };
+        folder_ids.push(created);
+    }
+
+    let mut items = Vec::new();
+    let mut unreached = HashMap::new();
+    for ((_, folder), folder_id) in flat.iter().zip(&folder_ids) {
+        for file in &folder.files {
+            let Some(convert) = file_action(file, options.unsupported, &settings) else {
+                continue;
+            };
+            let file_path = file.to_string_lossy().to_string();
+            match folder_id {
+                Ok(folder_id) => items.push(BatchItem {
+                    file_path,
+                    folder_id: Some(folder_id.clone()),
+                    convert,
+                    ocr_language: ocr_language.clone(),
+                    // Every upload goes into freshly created folders
+                    on_existing: ExistingFileMode::Create,
+                    resolved_from: None,
+                }),
+                Err(e) => {
+                    unreached.insert(file_path, e.clone());
+                }
+            }
+        }
+    }
+
+    let concurrency = UploadOptions {
+        concurrency: options.concurrency,
+        ..Default::default()
+    }
+    .concurrency();
+    let batch = run_batch(app, manager.inner().clone(), items, concurrency).await;
+
+    let outcomes: HashMap<String, FileUploadOutcome> = batch
+        .files
+        .into_iter()
+        .map(|outcome| (outcome.file_path.clone(), outcome))
+        .collect();
+
+    let mut next_folder = 0;
+    let root = summarize(&local, &folder_ids, &mut next_folder, &outcomes, &unreached);
+
+    let mut counts = (0, 0, 0);
+    count_files(&root, &mut counts);
+    let (uploaded, skipped, failed) = counts;
+
+    Ok(FolderUploadResult {
+        batch_id: batch.batch_id,
+        root,
+        uploaded,
+        skipped,
+        failed,
+    })
+}
+
+/// Read a directory tree, ignoring hidden entries and symlinks
+pub fn scan_directory(path: &Path) -> std::io::Result<LocalFolder> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string());
+
+    let mut files = Vec::new();
+    let mut folders = Vec::new();
+
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            folders.push(scan_directory(&entry.path())?);
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+
+    files.sort();
+    folders.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(LocalFolder {
+        name,
+        path: path.to_path_buf(),
+        files,
+        folders,
+    })
+}
+
+/// Whether to upload a file, and if so whether to convert it
+fn file_action(path: &Path, unsupported: UnsupportedFiles, settings: &Settings) -> Option<bool> {
+    if detect_file_type(path).is_some() {
+        return Some(settings.converts(path));
+    }
+    match unsupported {
+        UnsupportedFiles::Skip => None,
+        UnsupportedFiles::UploadUnconverted => Some(false),
+    }
+}
+
+/// Pre-order list of folders with the index of their parent
+fn flatten<'a>(
+    folder: &'a LocalFolder,
+    parent: Option<usize>,
+    out: &mut Vec<(Option<usize>, &'a LocalFolder)>,
+) {
+    let index = out.len();
+    out.push((parent, folder));
+    for child in &folder.folders {
+        flatten(child, Some(index), out);
+    }
+}
+
+/// Rebuild the tree with Drive IDs and outcomes; walks in the same order as `flatten`.
+///
+/// `unreached` holds the files that were never uploaded because their folder
+/// could not be created.
+fn summarize(
+    folder: &LocalFolder,
+    folder_ids: &[Result<String, GopenerError>],
+    next_folder: &mut usize,
+    outcomes: &HashMap<String, FileUploadOutcome>,
+    unreached: &HashMap<String, GopenerError>,
+) -> FolderSummary {
+    let (folder_id, error) = match &folder_ids[*next_folder] {
+        Ok(id) => (Some(id.clone()), None),
+        Err(e) => (None, Some(e.clone())),
+    };
+    *next_folder += 1;
+
+    let files = folder
+        .files
+        .iter()
+        .map(|path| file_summary(path, outcomes, unreached))
+        .collect();
+    let folders = folder
+        .folders
+        .iter()
+        .map(|child| summarize(child, folder_ids, next_folder, outcomes, unreached))
+        .collect();
+
+    FolderSummary {
+        name: folder.name.clone(),
+        local_path: folder.path.to_string_lossy().to_string(),
+        folder_id,
+        error,
+        files,
+        folders,
+    }
+}
+
+fn file_summary(
+    path: &Path,
+    outcomes: &HashMap<String, FileUploadOutcome>,
+    unreached: &HashMap<String, GopenerError>,
+) -> FileSummary {
+    let local_path = path.to_string_lossy().to_string();
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let Some(outcome) = outcomes.get(&local_path) else {
+        let error = unreached.get(&local_path).cloned();
+        return FileSummary {
+            name,
+            local_path,
+            status: if error.is_some() {
+                FileStatus::Failed
+            } else {
+                FileStatus::Skipped
+            },
+            job_id: None,
+            file_id: None,
+            web_view_link: None,
+            error,
+        };
+    };
+
+    let status = match outcome.state {
+        JobState::Succeeded if outcome.result.as_ref().is_some_and(|r| r.converted) => {
+            FileStatus::Converted
+        }
+        JobState::Succeeded => FileStatus::Unconverted,
+        JobState::Cancelled => FileStatus::Cancelled,
+        _ => FileStatus::Failed,
+    };
+
+    FileSummary {
+        name,
+        local_path,
+        status,
+        job_id: Some(outcome.job_id.clone()),
+        file_id: outcome.result.as_ref().map(|r| r.file_id.clone()),
+        web_view_link: outcome.result.as_ref().map(|r| r.web_view_link.clone()),
+        error: outcome.error.clone(),
+    }
+}
+
+/// Tally (uploaded, skipped, failed) over the whole tree
+fn count_files(folder: &FolderSummary, counts: &mut (usize, usize, usize)) {
+    for file in &folder.files {
+        match file.status {
+            FileStatus::Converted | FileStatus::Unconverted => counts.0 += 1,
+            FileStatus::Skipped => counts.1 += 1,
+            FileStatus::Failed | FileStatus::Cancelled => counts.2 += 1,
+        }
+    }
+    for child in &folder.folders {
+        count_files(child, counts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::upload::UploadResult;
+    use std::fs;
+
+    fn temp_tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("reports/2024")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("budget.xlsx"), b"x").unwrap();
+        fs::write(root.join("notes.txt"), b"x").unwrap();
+        fs::write(root.join(".DS_Store"), b"x").unwrap();
+        fs::write(root.join("reports/summary.docx"), b"x").unwrap();
+        fs::write(root.join("reports/logo.svg"), b"x").unwrap();
+        fs::write(root.join("reports/2024/q1.pptx"), b"x").unwrap();
+        fs::write(root.join(".git/config"), b"x").unwrap();
+        root
+    }
+
+    fn outcome(path: &Path, state: JobState) -> (String, FileUploadOutcome) {
+        let file_path = path.to_string_lossy().to_string();
+        let result = (state == JobState::Succeeded).then(|| UploadResult {
+            file_id: format!("id-{}", path.file_name().unwrap().to_string_lossy()),
+            name: "name".to_string(),
+            web_view_link: "https://drive.google.com".to_string(),
+            file_type: "Google Docs".to_string(),
+            converted: detect_file_type(path).is_some(),
+            updated_existing: false,
+            warning: None,
+        });
+        (
+            file_path.clone(),
+            FileUploadOutcome {
+                job_id: "upload-1".to_string(),
+                file_path,
+                state,
+                result,
+                error: (state == JobState::Failed)
+                    .then(|| GopenerError::Network("Upload interrupted".to_string())),
+            },
+        )
+    }
+
+    #[test]
+    fn test_scan_directory_skips_hidden_entries() {
+        let root = temp_tree("gopener_scan_tree");
+        let local = scan_directory(&root).unwrap();
+
+        assert_eq!(local.name, "gopener_scan_tree");
+        assert_eq!(
+            local.files,
+            vec![root.join("budget.xlsx"), root.join("notes.txt")]
+        );
+        assert_eq!(local.folders.len(), 1);
+        assert_eq!(local.folders[0].name, "reports");
+        assert_eq!(local.folders[0].folders[0].name, "2024");
+        assert_eq!(local.folders[0].folders[0].files.len(), 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_flatten_lists_parents_before_children() {
+        let root = temp_tree("gopener_flatten_tree");
+        let local = scan_directory(&root).unwrap();
+
+        let mut flat = Vec::new();
+        flatten(&local, None, &mut flat);
+        let shape: Vec<(Option<usize>, &str)> = flat
+            .iter()
+            .map(|(parent, folder)| (*parent, folder.name.as_str()))
+            .collect();
+
+        assert_eq!(
+            shape,
+            vec![
+                (None, "gopener_flatten_tree"),
+                (Some(0), "reports"),
+                (Some(1), "2024")
+            ]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_file_action() {
+        let settings = Settings::new();
+        let image = Path::new("/tmp/logo.svg");
+        assert_eq!(
+            file_action(Path::new("/tmp/a.docx"), UnsupportedFiles::Skip, &settings),
+            Some(true)
+        );
+        assert_eq!(file_action(image, UnsupportedFiles::Skip, &settings), None);
+        assert_eq!(
+            file_action(image, UnsupportedFiles::UploadUnconverted, &settings),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_kept_extensions_upload_unconverted() {
+        let mut settings = Settings::new();
+        settings.keep_original_extensions = vec!["xlsx".to_string()];
+        assert_eq!(
+            file_action(Path::new("/tmp/b.xlsx"), UnsupportedFiles::Skip, &settings),
+            Some(false)
+        );
+        assert_eq!(
+            file_action(Path::new("/tmp/a.docx"), UnsupportedFiles::Skip, &settings),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_summary_tree_mirrors_local_folders() {
+        let root = temp_tree("gopener_summary_tree");
+        let local = scan_directory(&root).unwrap();
+        let folder_ids = vec![
+            Ok("d-root".to_string()),
+            Ok("d-reports".to_string()),
+            Ok("d-2024".to_string()),
+        ];
+
+        let outcomes: HashMap<_, _> = [
+            outcome(&root.join("budget.xlsx"), JobState::Succeeded),
+            outcome(&root.join("notes.txt"), JobState::Failed),
+            outcome(&root.join("reports/summary.docx"), JobState::Succeeded),
+            outcome(&root.join("reports/logo.svg"), JobState::Succeeded),
+            outcome(&root.join("reports/2024/q1.pptx"), JobState::Cancelled),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut next = 0;
+        let summary = summarize(&local, &folder_ids, &mut next, &outcomes, &HashMap::new());
+
+        assert_eq!(summary.folder_id.as_deref(), Some("d-root"));
+        assert_eq!(summary.files[0].status, FileStatus::Converted);
+        assert_eq!(summary.files[0].file_id.as_deref(), Some("id-budget.xlsx"));
+        assert_eq!(summary.files[1].status, FileStatus::Failed);
+        assert_eq!(
+            summary.files[1].error.as_ref().map(GopenerError::code),
+            Some("network_error")
+        );
+
+        let reports = &summary.folders[0];
+        assert_eq!(reports.folder_id.as_deref(), Some("d-reports"));
+        let statuses: Vec<_> = reports
+            .files
+            .iter()
+            .map(|f| (f.name.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("logo.svg", FileStatus::Unconverted),
+                ("summary.docx", FileStatus::Converted)
+            ]
+        );
+        assert_eq!(reports.folders[0].folder_id.as_deref(), Some("d-2024"));
+        assert_eq!(reports.folders[0].files[0].status, FileStatus::Cancelled);
+
+        let mut counts = (0, 0, 0);
+        count_files(&summary, &mut counts);
+        assert_eq!(counts, (3, 0, 2));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_folders_that_failed_are_reported_with_their_files() {
+        let root = temp_tree("gopener_failed_folder_tree");
+        let local = scan_directory(&root).unwrap();
+        let refused = GopenerError::Network("connection reset".to_string());
+        let folder_ids = vec![
+            Ok("d-root".to_string()),
+            Err(refused.clone()),
+            Err(refused.clone()),
+        ];
+        let outcomes: HashMap<_, _> = [outcome(&root.join("budget.xlsx"), JobState::Succeeded)]
+            .into_iter()
+            .collect();
+        let unreached: HashMap<_, _> = [
+            root.join("reports/summary.docx"),
+            root.join("reports/2024/q1.pptx"),
+        ]
+        .iter()
+        .map(|path| (path.to_string_lossy().to_string(), refused.clone()))
+        .collect();
+
+        let mut next = 0;
+        let summary = summarize(&local, &folder_ids, &mut next, &outcomes, &unreached);
+
+        assert!(summary.error.is_none());
+        let reports = &summary.folders[0];
+        assert!(reports.folder_id.is_none());
+        assert_eq!(reports.error, Some(refused.clone()));
+        let statuses: Vec<_> = reports
+            .files
+            .iter()
+            .map(|f| (f.name.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("logo.svg", FileStatus::Skipped),
+                ("summary.docx", FileStatus::Failed)
+            ]
+        );
+        assert_eq!(reports.folders[0].files[0].error, Some(refused));
+
+        let mut counts = (0, 0, 0);
+        count_files(&summary, &mut counts);
+        // notes.txt had no outcome here, so it counts as skipped
+        assert_eq!(counts, (1, 2, 2));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_files_without_outcome_are_skipped() {
+        let outcomes = HashMap::new();
+        let summary = file_summary(Path::new("/tmp/logo.svg"), &outcomes, &HashMap::new());
+        assert_eq!(summary.status, FileStatus::Skipped);
+        assert!(summary.job_id.is_none());
+    }
+
+    #[test]
+    fn test_options_default_to_skipping() {
+        let options: FolderUploadOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(options.unsupported, UnsupportedFiles::Skip);
+
+        let options: FolderUploadOptions =
+            serde_json::from_str(r#"{"unsupported": "upload_unconverted"}"#).unwrap();
+        assert_eq!(options.unsupported, UnsupportedFiles::UploadUnconverted);
+    }
+}
@@ -0,0 +1,9 @@
+//! Supported formats for the frontend's file picker and type labels.
+
+use crate::utils::formats::{Format, FORMATS};
+
+/// Every format Gopener uploads, with its Google target and conversion limits
+#[tauri::command]
+pub async fn get_supported_formats() -> Vec<Format> {
+    FORMATS.to_vec()
+}
@@ -0,0 +1,567 @@
+//! Upload job bookkeeping.
+//!
+//! Every upload is registered with the [`UploadManager`] kept in Tauri managed
+//! state, so concurrent uploads each track their own state and progress.
+
+use crate::commands::upload::{UploadProgress, UploadResult};
+use crate::error::GopenerError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tauri::State;
+use tokio::sync::watch;
+
+/// Finished jobs kept around for the UI before the oldest are dropped
+const MAX_FINISHED_JOBS: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobState::Succeeded | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UploadJob {
+    pub id: String,
+    pub batch_id: Option<String>,
+    pub file_path: String,
+    pub folder_id: Option<String>,
+    pub state: JobState,
+    pub progress: UploadProgress,
+    pub result: Option<UploadResult>,
+    pub error: Option<GopenerError>,
+}
+
+/// Payload of the `upload-cancelled` event
+#[derive(Debug, Serialize, Clone)]
+pub struct UploadCancelled {
+    pub job_id: String,
+}
+
+struct Entry {
+    job: UploadJob,
+    cancel: watch::Sender<bool>,
+    session_url: Option<String>,
+}
+
+#[derive(Default)]
+struct Jobs {
+    next_id: u64,
+    next_batch_id: u64,
+    entries: Vec<Entry>,
+}
+
+/// Registry of upload jobs, cheap to clone into background tasks
+#[derive(Clone, Default)]
+pub struct UploadManager {
+    inner: Arc<Mutex<Jobs>>,
+}
+
+impl UploadManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new queued job and return its ID
+    pub fn create(&self, file_path: &str, folder_id: Option<String>) -> String {
+        let mut inner = self.inner.lock().unwrap();
+        push_job(&mut inner, None, file_path, folder_id)
+    }
+
+    /// Register a new queued job under an ID the caller chose, so the caller can
+    /// tell its job apart and cancel it before any progress is reported
+    pub fn create_with_id(
+        &self,
+        id: String,
+        file_path: &str,
+        folder_id: Option<String>,
+    ) -> Result<String, GopenerError> {
+        let mut inner = self.inner.lock().unwrap();
+        if id.trim().is_empty() || inner.entries.iter().any(|e| e.job.id == id) {
+            return Err(GopenerError::InvalidInput(format!(
+                "Upload job ID is empty or already taken: {}",
+                id
+            )));
+        }
+        insert_job(&mut inner, id.clone(), None, file_path, folder_id);
+        Ok(id)
+    }
+
+    /// Register one queued job per `(file_path, folder_id)` under a shared batch ID
+    pub fn create_batch(&self, files: &[(String, Option<String>)]) -> (String, Vec<String>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_batch_id += 1;
+        let batch_id = format!("batch-{}", inner.next_batch_id);
+
+        let job_ids = files
+            .iter()
+            .map(|(path, folder_id)| push_job(&mut inner, Some(&batch_id), path, folder_id.clone()))
+            .collect();
+
+        (batch_id, job_ids)
+    }
+
+    /// Record the folder a job uploads into once its path has been resolved
+    pub fn set_folder(&self, id: &str, folder_id: Option<String>) {
+        self.update(id, |job| job.folder_id = folder_id);
+    }
+
+    pub fn start(&self, id: &str) {
+        self.update(id, |job| job.state = JobState::Running);
+    }
+
+    pub fn set_progress(&self, id: &str, progress: UploadProgress) {
+        self.update(id, |job| job.progress = progress);
+    }
+
+    pub fn succeed(&self, id: &str, result: UploadResult) {
+        self.update(id, |job| {
+            job.state = JobState::Succeeded;
+            job.result = Some(result);
+        });
+    }
+
+    pub fn fail(&self, id: &str, error: &GopenerError) {
+        self.update(id, |job| {
+            job.state = JobState::Failed;
+            job.error = Some(error.clone());
+        });
+    }
+
+    pub fn mark_cancelled(&self, id: &str) {
+        self.update(id, |job| job.state = JobState::Cancelled);
+    }
+
+    /// Remember the resumable session of a running job so it can be discarded on cancel
+    pub fn set_session_url(&self, id: &str, session_url: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(entry) = inner.entries.iter_mut().find(|e| e.job.id == id) {
+            entry.session_url = Some(session_url.to_string());
+        }
+    }
+
+    pub fn session_url(&self, id: &str) -> Option<String> {
+        let inner = self.inner.lock().unwrap();
+        let entry = inner.entries.iter().find(|e| e.job.id == id)?;
+        entry.session_url.clone()
+    }
+
+    /// Receiver that flips to `true` once the job is asked to cancel
+    pub fn cancel_signal(&self, id: &str) -> Option<watch::Receiver<bool>> {
+        let inner = self.inner.lock().unwrap();
+        let entry = inner.entries.iter().find(|e| e.job.id == id)?;
+        Some(entry.cancel.subscribe())
+    }
+
+    /// Ask a queued or running job to stop.
+    ///
+    /// The task running the job observes the signal, cleans up and marks the job
+    /// cancelled.
+    pub fn cancel(&self, id: &str) -> Result<(), GopenerError> {
+        let inner = self.inner.lock().unwrap();
+        let entry = inner
+            .entries
+            .iter()
+            .find(|e| e.job.id == id)
+            .ok_or_else(|| GopenerError::JobNotFound(id.to_string()))?;
+
+        if entry.job.state.is_finished() {
+            return Err(GopenerError::JobFinished(id.to_string()));
+        }
+        entry.cancel.send_replace(true);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<UploadJob> {
+        let inner = self.inner.lock().unwrap();
+        let entry = inner.entries.iter().find(|e| e.job.id == id)?;
+        Some(entry.job.clone())
+    }
+
+    /// All known jobs, oldest first
+    pub fn list(&self) -> Vec<UploadJob> {
+        let inner = self.inner.lock().unwrap();
+        inner.entries.iter().map(|e| e.job.clone()).collect()
+    }
+
+    fn update(&self, id: &str, apply: impl FnOnce(&mut UploadJob)) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(entry) = inner.entries.iter_mut().find(|e| e.job.id == id) {
+            // A finished job never changes state again
+            if !entry.job.state.is_finished() {
+                apply(&mut entry.job);
+            }
+            if entry.job.state.is_finished() {
+                entry.session_url = None;
+            }
+        }
+    }
+}
+
+fn push_job(
+    inner: &mut Jobs,
+    batch_id: Option<&str>,
+    file_path: &str,
+    folder_id: Option<String>,
+) -> String {
+    inner.next_id += 1;
+    let id = format!("upload-{}", inner.next_id);
+    insert_job(inner, id.clone(), batch_id, file_path, folder_id);
+    id
+}
+
+fn insert_job(
+    inner: &mut Jobs,
+    id: String,
+    batch_id: Option<&str>,
+    file_path: &str,
+    folder_id: Option<String>,
+) {
+    let (cancel, _) = watch::channel(false);
+    inner.entries.push(Entry {
+        job: UploadJob {
+            id: id.clone(),
+            batch_id: batch_id.map(str::to_string),
+            file_path: file_path.to_string(),
+            folder_id,
+            state: JobState::Queued,
+            progress: UploadProgress::empty(&id),
+            result: None,
+            error: None,
+        },
+        cancel,
+        session_url: None,
+    });
+    prune_finished(&mut inner.entries);
+}
+
+/// Run `work` until it completes or the job is cancelled.
+///
+/// Returns `None` on cancellation; `work` is dropped at that point, which
+/// aborts any request it had in flight.
+pub async fn until_cancelled<F: Future>(
+    signal: &mut watch::Receiver<bool>,
+    work: F,
+) -> Option<F::Output> {
+    tokio::select! {
+        biased;
+        _ = signal.wait_for(|cancelled| *cancelled) => None,
+        output = work => Some(output),
+    }
+}
+
+/// Drop the oldest finished jobs past `MAX_FINISHED_JOBS`, keeping every job
+/// of a batch that is still running so the batch can report on it
+fn prune_finished(entries: &mut Vec<Entry>) {
+    let running_batches: HashSet<String> = entries
+        .iter()
+        .filter(|e| !e.job.state.is_finished())
+        .filter_map(|e| e.job.batch_id.clone())
+        .collect();
+    let finished = entries.iter().filter(|e| e.job.state.is_finished()).count();
+    let mut excess = finished.saturating_sub(MAX_FINISHED_JOBS);
+    entries.retain(|e| {
+        let in_running_batch = e
+            .job
+            .batch_id
+            .as_ref()
+            .is_some_and(|batch_id| running_batches.contains(batch_id));
+        if excess > 0 && e.job.state.is_finished() && !in_running_batch {
+            excess -= 1;
+            false
+        } else {
+            true
+        }
+    });
+}
+
+/// List all upload jobs known to this session
+#[tauri::command]
+pub fn list_upload_jobs(manager: State<'_, UploadManager>) -> Vec<UploadJob> {
+    manager.list()
+}
+
+/// Get a single upload job by ID
+#[tauri::command]
+pub fn get_upload_job(
+    manager: State<'_, UploadManager>,
+    job_id: String,
+) -> Result<UploadJob, GopenerError> {
+    manager
+        .get(&job_id)
+        .ok_or(GopenerError::JobNotFound(job_id))
+}
+
+/// Cancel a queued or running upload
+#[tauri::command]
+pub fn cancel_upload(
+    manager: State<'_, UploadManager>,
+    job_id: String,
+) -> Result<(), GopenerError> {
+    manager.cancel(&job_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(id: &str) -> UploadResult {
+        UploadResult {
+            file_id: id.to_string(),
+            name: "report".to_string(),
+            web_view_link: format!("https://docs.google.com/document/d/{}", id),
+            file_type: "Google Docs".to_string(),
+            converted: true,
+            updated_existing: false,
+            warning: None,
+        }
+    }
+
+    #[test]
+    fn test_jobs_get_unique_ids() {
+        let manager = UploadManager::new();
+        let first = manager.create("/tmp/a.docx", None);
+        let second = manager.create("/tmp/b.docx", Some("folder".to_string()));
+
+        assert_ne!(first, second);
+        assert_eq!(manager.list().len(), 2);
+        assert_eq!(
+            manager.get(&second).unwrap().folder_id.as_deref(),
+            Some("folder")
+        );
+        assert_eq!(manager.get(&first).unwrap().state, JobState::Queued);
+    }
+
+    #[test]
+    fn test_batch_jobs_share_batch_id() {
+        let manager = UploadManager::new();
+        let files = vec![
+            ("/tmp/a.xlsx".to_string(), None),
+            ("/tmp/b.xlsx".to_string(), Some("folder".to_string())),
+        ];
+        let (batch_id, job_ids) = manager.create_batch(&files);
+        let single = manager.create("/tmp/c.xlsx", None);
+
+        assert_eq!(job_ids.len(), 2);
+        for (id, (path, folder_id)) in job_ids.iter().zip(&files) {
+            let job = manager.get(id).unwrap();
+            assert_eq!(job.batch_id.as_deref(), Some(batch_id.as_str()));
+            assert_eq!(&job.file_path, path);
+            assert_eq!(&job.folder_id, folder_id);
+        }
+        assert!(manager.get(&single).unwrap().batch_id.is_none());
+    }
+
+    #[test]
+    fn test_job_lifecycle() {
+        let manager = UploadManager::new();
+        let id = manager.create("/tmp/a.docx", None);
+
+        manager.start(&id);
+        assert_eq!(manager.get(&id).unwrap().state, JobState::Running);
+
+        let mut progress = UploadProgress::empty(&id);
+        progress.bytes_uploaded = 10;
+        progress.total_bytes = 20;
+        manager.set_progress(&id, progress);
+        assert_eq!(manager.get(&id).unwrap().progress.bytes_uploaded, 10);
+
+        manager.succeed(&id, result("file-1"));
+        let job = manager.get(&id).unwrap();
+        assert_eq!(job.state, JobState::Succeeded);
+        assert_eq!(job.result.unwrap().file_id, "file-1");
+    }
+
+    #[test]
+    fn test_progress_is_tracked_per_job() {
+        let manager = UploadManager::new();
+        let first = manager.create("/tmp/a.docx", None);
+        let second = manager.create("/tmp/b.docx", None);
+
+        let mut progress = UploadProgress::empty(&first);
+        progress.bytes_uploaded = 5;
+        manager.set_progress(&first, progress);
+
+        assert_eq!(manager.get(&first).unwrap().progress.bytes_uploaded, 5);
+        assert_eq!(manager.get(&second).unwrap().progress.bytes_uploaded, 0);
+    }
+
+    #[test]
+    fn test_finished_jobs_do_not_change() {
+        let manager = UploadManager::new();
+        let id = manager.create("/tmp/a.docx", None);
+
+        let error = GopenerError::Network("Upload interrupted".to_string());
+        manager.fail(&id, &error);
+        manager.succeed(&id, result("file-1"));
+
+        let job = manager.get(&id).unwrap();
+        assert_eq!(job.state, JobState::Failed);
+        assert_eq!(job.error, Some(error));
+        assert!(job.result.is_none());
+    }
+
+    #[test]
+    fn test_unknown_job() {
+        let manager = UploadManager::new();
+        assert!(manager.get("upload-42").is_none());
+        assert_eq!(
+            manager.cancel("upload-42"),
+            Err(GopenerError::JobNotFound("upload-42".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_cancel_signals_job() {
+        let manager = UploadManager::new();
+        let id = manager.create("/tmp/a.docx", None);
+        let signal = manager.cancel_signal(&id).unwrap();
+        assert!(!*signal.borrow());
+
+        manager.cancel(&id).unwrap();
+        assert!(*signal.borrow());
+
+        // The runner decides when the job is actually cancelled
+        assert_eq!(manager.get(&id).unwrap().state, JobState::Queued);
+        manager.mark_cancelled(&id);
+        assert_eq!(manager.get(&id).unwrap().state, JobState::Cancelled);
+    }
+
+    #[test]
+    fn test_cannot_cancel_finished_job() {
+        let manager = UploadManager::new();
+        let id = manager.create("/tmp/a.docx", None);
+        manager.succeed(&id, result("file-1"));
+
+        assert_eq!(
+            manager.cancel(&id),
+            Err(GopenerError::JobFinished(id.clone()))
+        );
+        assert!(!*manager.cancel_signal(&id).unwrap().borrow());
+    }
+
+    #[test]
+    fn test_session_url_is_dropped_when_job_finishes() {
+        let manager = UploadManager::new();
+        let id = manager.create("/tmp/big.xlsx", None);
+        manager.set_session_url(&id, "https://upload/session-1");
+        assert_eq!(
+            manager.session_url(&id).as_deref(),
+            Some("https://upload/session-1")
+        );
+
+        manager.mark_cancelled(&id);
+        assert!(manager.session_url(&id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_until_cancelled_stops_work() {
+        let manager = UploadManager::new();
+        let id = manager.create("/tmp/a.docx", None);
+        let mut signal = manager.cancel_signal(&id).unwrap();
+
+        let canceller = manager.clone();
+        let job_id = id.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            canceller.cancel(&job_id).unwrap();
+        });
+
+        let outcome = until_cancelled(&mut signal, std::future::pending::<()>()).await;
+        assert!(outcome.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_until_cancelled_returns_output() {
+        let manager = UploadManager::new();
+        let id = manager.create("/tmp/a.docx", None);
+        let mut signal = manager.cancel_signal(&id).unwrap();
+
+        assert_eq!(until_cancelled(&mut signal, async { 42 }).await, Some(42));
+    }
+
+    #[test]
+    fn test_old_finished_jobs_are_pruned() {
+        let manager = UploadManager::new();
+        let running = manager.create("/tmp/running.docx", None);
+        manager.start(&running);
+
+        for i in 0..MAX_FINISHED_JOBS + 5 {
+            let id = manager.create(&format!("/tmp/{}.docx", i), None);
+            manager.succeed(&id, result(&id));
+        }
+        manager.create("/tmp/last.docx", None);
+
+        let jobs = manager.list();
+        let finished = jobs.iter().filter(|job| job.state.is_finished()).count();
+        assert_eq!(finished, MAX_FINISHED_JOBS);
+        assert!(manager.get(&running).is_some());
+        assert!(manager.get("upload-2").is_none());
+    }
+
+    #[test]
+    fn test_jobs_can_use_the_callers_id() {
+        let manager = UploadManager::new();
+        let id = manager
+            .create_with_id("window-1".to_string(), "/tmp/a.docx", None)
+            .unwrap();
+        assert_eq!(id, "window-1");
+
+        manager.set_folder(&id, Some("folder".to_string()));
+        let job = manager.get("window-1").unwrap();
+        assert_eq!(job.state, JobState::Queued);
+        assert_eq!(job.folder_id.as_deref(), Some("folder"));
+        manager.cancel("window-1").unwrap();
+
+        let taken = manager.create_with_id("window-1".to_string(), "/tmp/b.docx", None);
+        assert!(matches!(taken, Err(GopenerError::InvalidInput(_))));
+        let empty = manager.create_with_id(" ".to_string(), "/tmp/b.docx", None);
+        assert!(matches!(empty, Err(GopenerError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_running_batches_are_not_pruned() {
+        let manager = UploadManager::new();
+        let files: Vec<(String, Option<String>)> = (0..MAX_FINISHED_JOBS + 5)
+            .map(|i| (format!("/tmp/{}.docx", i), None))
+            .collect();
+        let (_, job_ids) = manager.create_batch(&files);
+        for id in &job_ids[1..] {
+            manager.succeed(id, result(id));
+        }
+        manager.create("/tmp/quick.docx", None);
+
+        assert!(job_ids.iter().all(|id| manager.get(id).is_some()));
+
+        manager.succeed(&job_ids[0], result(&job_ids[0]));
+        manager.create("/tmp/later.docx", None);
+        let finished = manager
+            .list()
+            .iter()
+            .filter(|j| j.state.is_finished())
+            .count();
+        assert_eq!(finished, MAX_FINISHED_JOBS);
+    }
+
+    #[test]
+    fn test_job_state_serializes_lowercase() {
+        assert_eq!(
+            serde_json::to_string(&JobState::Cancelled).unwrap(),
+            "\"cancelled\""
+        );
+    }
+}
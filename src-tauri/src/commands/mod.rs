@@ -1,5 +1,9 @@
 pub mod auth;
 pub mod fileassoc;
+pub mod folder_upload;
+pub mod formats;
+pub mod jobs;
 pub mod oauth_config;
 pub mod storage;
 pub mod upload;
+pub mod upload_db;
@@ -1,3 +1,4 @@
+use crate::error::GopenerError;
 use crate::utils::keychain::{self, keys};
 use serde::{Deserialize, Serialize};
 
@@ -10,11 +11,10 @@ pub struct OAuthConfig {
 
 /// Get the current OAuth configuration
 #[tauri::command]
-pub async fn get_oauth_config() -> Result<OAuthConfig, String> {
-    let client_id = keychain::retrieve(keys::CUSTOM_CLIENT_ID).map_err(|e| e.to_string())?;
+pub async fn get_oauth_config() -> Result<OAuthConfig, GopenerError> {
+    let client_id = keychain::retrieve(keys::CUSTOM_CLIENT_ID)?;
 
-    let has_secret = keychain::retrieve(keys::CUSTOM_CLIENT_SECRET)
-        .map_err(|e| e.to_string())?
+    let has_secret = keychain::retrieve(keys::CUSTOM_CLIENT_SECRET)?
         .map(|s| !s.is_empty())
         .unwrap_or(false);
 
@@ -29,21 +29,26 @@ pub async fn get_oauth_config() -> Result<OAuthConfig, String> {
 
 /// Save custom OAuth credentials
 #[tauri::command]
-pub async fn save_oauth_config(client_id: String, client_secret: String) -> Result<(), String> {
+pub async fn save_oauth_config(
+    client_id: String,
+    client_secret: String,
+) -> Result<(), GopenerError> {
     // Validate client ID format (basic validation)
     if !client_id.contains(".apps.googleusercontent.com") {
-        return Err("Invalid client ID format. It should end with .apps.googleusercontent.com".to_string());
+        return Err(GopenerError::InvalidInput(
+            "Invalid client ID format. It should end with .apps.googleusercontent.com".to_string(),
+        ));
     }
 
-    keychain::store(keys::CUSTOM_CLIENT_ID, &client_id).map_err(|e| e.to_string())?;
-    keychain::store(keys::CUSTOM_CLIENT_SECRET, &client_secret).map_err(|e| e.to_string())?;
+    keychain::store(keys::CUSTOM_CLIENT_ID, &client_id)?;
+    keychain::store(keys::CUSTOM_CLIENT_SECRET, &client_secret)?;
 
     Ok(())
 }
 
 /// Clear custom OAuth credentials and revert to default
 #[tauri::command]
-pub async fn clear_oauth_config() -> Result<(), String> {
+pub async fn clear_oauth_config() -> Result<(), GopenerError> {
     let _ = keychain::delete(keys::CUSTOM_CLIENT_ID);
     let _ = keychain::delete(keys::CUSTOM_CLIENT_SECRET);
     Ok(())
@@ -1,6 +1,9 @@
+use crate::error::GopenerError;
+use crate::utils::retry::RetryPolicy;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RecentFile {
@@ -16,10 +19,21 @@ pub struct RecentFile {
 pub struct Settings {
     pub default_folder_id: Option<String>,
     pub default_folder_name: Option<String>,
+    /// Readable default destination such as `Clients/Acme`, resolved when no ID is saved
+    pub default_folder_path: Option<String>,
     pub auto_open_after_upload: bool,
     pub auto_close_after_upload: bool,
     pub theme: String,
     pub recent_files: Vec<RecentFile>,
+    /// Extensions such as `xlsm` uploaded as they are instead of converted
+    #[serde(default)]
+    pub keep_original_extensions: Vec<String>,
+    /// Language of the text in PDFs and images, e.g. `en`, unless an upload names one
+    pub ocr_language: Option<String>,
+    /// Tries per request to Google, including the first; the built-in default when unset
+    pub max_request_attempts: Option<u32>,
+    /// Longest wait between two tries, in seconds; the built-in default when unset
+    pub max_retry_delay_secs: Option<u64>,
 }
 
 impl Settings {
@@ -27,58 +41,98 @@ impl Settings {
         Settings {
             default_folder_id: None,
             default_folder_name: None,
+            default_folder_path: None,
             auto_open_after_upload: true,
             auto_close_after_upload: false,
             theme: "dark".to_string(),
             recent_files: Vec::new(),
+            keep_original_extensions: Vec::new(),
+            ocr_language: None,
+            max_request_attempts: None,
+            max_retry_delay_secs: None,
         }
     }
+
+    /// How requests to Google are retried, with the limits set here
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            max_attempts: self
+                .max_request_attempts
+                .unwrap_or(default.max_attempts)
+                .max(1),
+            max_delay: self
+                .max_retry_delay_secs
+                .map_or(default.max_delay, Duration::from_secs),
+            ..default
+        }
+    }
+
+    /// Whether `path` is converted to a Google format when the upload doesn't say
+    pub fn converts(&self, path: &Path) -> bool {
+        let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+            return true;
+        };
+        !self
+            .keep_original_extensions
+            .iter()
+            .any(|kept| kept.trim_start_matches('.').eq_ignore_ascii_case(extension))
+    }
 }
 
-/// Get the settings file path
-fn get_settings_path() -> Result<PathBuf, String> {
-    let config_dir = dirs::config_dir().ok_or("Could not find config directory")?;
+/// Gopener's directory in the user's config dir, created on first use
+pub fn app_config_dir() -> Result<PathBuf, GopenerError> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| GopenerError::Io("Could not find config directory".to_string()))?;
     let app_dir = config_dir.join("gopener");
 
     // Create directory if it doesn't exist
     if !app_dir.exists() {
-        fs::create_dir_all(&app_dir).map_err(|e| format!("Failed to create config dir: {}", e))?;
+        fs::create_dir_all(&app_dir)
+            .map_err(|e| GopenerError::Io(format!("Failed to create config dir: {}", e)))?;
     }
 
-    Ok(app_dir.join("settings.json"))
+    Ok(app_dir)
+}
+
+/// Get the settings file path
+fn get_settings_path() -> Result<PathBuf, GopenerError> {
+    Ok(app_config_dir()?.join("settings.json"))
 }
 
 /// Load settings from disk
 #[tauri::command]
-pub async fn get_settings() -> Result<Settings, String> {
+pub async fn get_settings() -> Result<Settings, GopenerError> {
     let path = get_settings_path()?;
 
     if !path.exists() {
         return Ok(Settings::new());
     }
 
-    let contents =
-        fs::read_to_string(&path).map_err(|e| format!("Failed to read settings: {}", e))?;
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| GopenerError::Io(format!("Failed to read settings: {}", e)))?;
 
-    serde_json::from_str(&contents).map_err(|e| format!("Failed to parse settings: {}", e))
+    serde_json::from_str(&contents)
+        .map_err(|e| GopenerError::Io(format!("Failed to parse settings: {}", e)))
 }
 
 /// Save settings to disk
 #[tauri::command]
-pub async fn save_settings(settings: Settings) -> Result<(), String> {
+pub async fn save_settings(settings: Settings) -> Result<(), GopenerError> {
     let path = get_settings_path()?;
 
-    let contents =
-        serde_json::to_string_pretty(&settings).map_err(|e| format!("Failed to serialize: {}", e))?;
+    let contents = serde_json::to_string_pretty(&settings)
+        .map_err(|e| GopenerError::Internal(format!("Failed to serialize: {}", e)))?;
 
-    fs::write(&path, contents).map_err(|e| format!("Failed to write settings: {}", e))?;
+    fs::write(&path, contents)
+        .map_err(|e| GopenerError::Io(format!("Failed to write settings: {}", e)))?;
 
     Ok(())
 }
 
 /// Add a file to recent files history
 #[tauri::command]
-pub async fn add_recent_file(file: RecentFile) -> Result<(), String> {
+pub async fn add_recent_file(file: RecentFile) -> Result<(), GopenerError> {
     let mut settings = get_settings().await?;
 
     // Remove if already exists (to move to top)
@@ -95,7 +149,7 @@ pub async fn add_recent_file(file: RecentFile) -> Result<(), String> {
 
 /// Clear all recent files
 #[tauri::command]
-pub async fn clear_recent_files() -> Result<(), String> {
+pub async fn clear_recent_files() -> Result<(), GopenerError> {
     let mut settings = get_settings().await?;
     settings.recent_files.clear();
     save_settings(settings).await
@@ -146,16 +200,51 @@ mod tests {
         let settings: Settings = serde_json::from_str(json).unwrap();
         assert_eq!(settings.default_folder_id, Some("folder-123".to_string()));
         assert_eq!(settings.default_folder_name, Some("My Folder".to_string()));
+        // Settings saved before destinations could be paths have none
+        assert_eq!(settings.default_folder_path, None);
+        assert!(settings.keep_original_extensions.is_empty());
         assert!(!settings.auto_open_after_upload);
         assert!(settings.auto_close_after_upload);
         assert_eq!(settings.theme, "light");
         assert!(settings.recent_files.is_empty());
     }
 
+    #[test]
+    fn test_kept_extensions_are_not_converted() {
+        let mut settings = Settings::new();
+        assert!(settings.converts(Path::new("/tmp/budget.xlsx")));
+
+        settings.keep_original_extensions = vec!["xlsx".to_string(), ".DOCX".to_string()];
+        assert!(!settings.converts(Path::new("/tmp/budget.XLSX")));
+        assert!(!settings.converts(Path::new("/tmp/letter.docx")));
+        assert!(settings.converts(Path::new("/tmp/deck.pptx")));
+        assert!(settings.converts(Path::new("/tmp/README")));
+    }
+
+    #[test]
+    fn test_retry_policy_uses_configured_limits() {
+        let mut settings = Settings::new();
+        let default = RetryPolicy::default();
+        assert_eq!(settings.retry_policy().max_attempts, default.max_attempts);
+        assert_eq!(settings.retry_policy().max_delay, default.max_delay);
+
+        settings.max_request_attempts = Some(2);
+        settings.max_retry_delay_secs = Some(5);
+        let policy = settings.retry_policy();
+        assert_eq!(policy.max_attempts, 2);
+        assert_eq!(policy.max_delay, Duration::from_secs(5));
+        assert!(policy.idempotent);
+
+        // The first try always happens
+        settings.max_request_attempts = Some(0);
+        assert_eq!(settings.retry_policy().max_attempts, 1);
+    }
+
     #[test]
     fn test_settings_roundtrip() {
         let mut settings = Settings::new();
         settings.default_folder_id = Some("abc".to_string());
+        settings.default_folder_path = Some("Clients/Acme".to_string());
         settings.theme = "light".to_string();
         settings.recent_files.push(RecentFile {
             id: "file-1".to_string(),
@@ -170,6 +259,7 @@ mod tests {
         let deserialized: Settings = serde_json::from_str(&json).unwrap();
 
         assert_eq!(deserialized.default_folder_id, settings.default_folder_id);
+        assert_eq!(deserialized.default_folder_path, settings.default_folder_path);
         assert_eq!(deserialized.theme, settings.theme);
         assert_eq!(deserialized.recent_files.len(), 1);
         assert_eq!(deserialized.recent_files[0].id, "file-1");
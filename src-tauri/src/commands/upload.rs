@@ -1,170 +1,891 @@
 use crate::commands::auth::get_valid_token;
+use crate::commands::jobs::{until_cancelled, JobState, UploadCancelled, UploadJob, UploadManager};
+use crate::commands::storage::{get_settings, Settings};
+use crate::commands::upload_db::{UploadHistory, UploadRecord};
 use crate::config;
-use crate::utils::file::{detect_file_type, get_file_info, get_mime_type};
+use crate::error::GopenerError;
+use crate::google::client::GoogleClient;
+use crate::google::drive::folder_path::{self, FolderPathCache};
+use crate::google::upload::{self, ProgressCallback, ResumableConfig, UploadTarget};
+use crate::utils::file::{
+    content_hash, detect_file_type, get_file_info, get_mime_type, is_encrypted,
+    original_format_name, preflight, sniff_format, GoogleFileType, Preflight,
+};
+use crate::utils::progress::ProgressTracker;
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
-use std::sync::atomic::{AtomicU64, Ordering};
-use tauri::Emitter;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Manager, State};
+
+/// Message of the error returned by upload commands when the user cancelled the job
+pub const UPLOAD_CANCELLED: &str = "Upload cancelled";
+
+/// `file_type` reported for files stored without conversion
+pub const UNCONVERTED_FILE_TYPE: &str = "Original format";
+
+/// Minimum gap between two `upload-progress` events for the same upload
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Files uploaded at the same time when the caller does not say otherwise
+const DEFAULT_CONCURRENCY: usize = 3;
+const MAX_CONCURRENCY: usize = 8;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UploadResult {
     pub file_id: String,
     pub name: String,
     pub web_view_link: String,
+    /// Google format the file was converted to, or the format it was kept in
     pub file_type: String,
+    /// Drive converted the file to a Google format
+    #[serde(default)]
+    pub converted: bool,
+    /// A revision was added to the file from an earlier upload instead of creating a copy
+    #[serde(default)]
+    pub updated_existing: bool,
+    /// Something the user should know, such as an extension that didn't match the content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// What to do with a file that was uploaded before
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExistingFileMode {
+    /// Replace the content of the Drive file it was uploaded to, adding a revision
+    Update,
+    /// Upload a new copy and leave the earlier one alone
+    Create,
+    /// Fail with `already_uploaded` so the user can pick one of the above
+    #[default]
+    Ask,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UploadProgress {
+    pub job_id: String,
     pub bytes_uploaded: u64,
     pub total_bytes: u64,
     pub percentage: f64,
+    pub bytes_per_second: f64,
+    pub eta_seconds: Option<f64>,
 }
 
-// Global progress tracking (simplified for single file uploads)
-static UPLOAD_PROGRESS: AtomicU64 = AtomicU64::new(0);
-static UPLOAD_TOTAL: AtomicU64 = AtomicU64::new(0);
+impl UploadProgress {
+    pub fn empty(job_id: &str) -> Self {
+        Self {
+            job_id: job_id.to_string(),
+            bytes_uploaded: 0,
+            total_bytes: 0,
+            percentage: 0.0,
+            bytes_per_second: 0.0,
+            eta_seconds: None,
+        }
+    }
+
+    fn from_tracker(job_id: &str, tracker: &ProgressTracker) -> Self {
+        Self {
+            job_id: job_id.to_string(),
+            bytes_uploaded: tracker.bytes(),
+            total_bytes: tracker.total(),
+            percentage: tracker.percentage(),
+            bytes_per_second: tracker.bytes_per_second(),
+            eta_seconds: tracker.eta_seconds(),
+        }
+    }
+}
+
+/// Options for batch uploads
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct UploadOptions {
+    /// Maximum number of files uploading at once
+    pub concurrency: Option<usize>,
+    /// What to do with files uploaded before
+    pub on_existing: ExistingFileMode,
+    /// Convert to Google formats; by default only extensions not kept in Settings are
+    pub convert: Option<bool>,
+    /// Language of the text in PDFs and images; defaults to the one in Settings
+    pub ocr_language: Option<String>,
+}
+
+impl UploadOptions {
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+            .unwrap_or(DEFAULT_CONCURRENCY)
+            .clamp(1, MAX_CONCURRENCY)
+    }
+}
+
+/// Payload of the `upload-batch-progress` event
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BatchProgress {
+    pub batch_id: String,
+    pub total_files: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub bytes_uploaded: u64,
+    pub total_bytes: u64,
+    pub percentage: f64,
+}
+
+/// Outcome of one file in a batch
+#[derive(Debug, Serialize, Clone)]
+pub struct FileUploadOutcome {
+    pub job_id: String,
+    pub file_path: String,
+    pub state: JobState,
+    pub result: Option<UploadResult>,
+    pub error: Option<GopenerError>,
+}
+
+/// One file to upload as part of a batch
+#[derive(Debug, Clone)]
+pub struct BatchItem {
+    pub file_path: String,
+    pub folder_id: Option<String>,
+    /// Convert to the matching Google format; otherwise keep the file as is
+    pub convert: bool,
+    /// Language hint for text recognition, used for converted PDFs and images
+    pub ocr_language: Option<String>,
+    pub on_existing: ExistingFileMode,
+    /// Drive path `folder_id` was resolved from, resolved again if the folder is gone
+    pub resolved_from: Option<FolderPathSource>,
+}
+
+/// Slash-separated Drive path below a base folder, or below My Drive without one
+#[derive(Debug, Clone)]
+pub struct FolderPathSource {
+    pub base_id: Option<String>,
+    pub path: String,
+}
+
+impl FolderPathSource {
+    /// Normalized `path` below `base_id`, if it names any folder at all
+    fn new(base_id: Option<String>, path: Option<String>) -> Option<Self> {
+        let path = folder_path::normalize(&path?);
+        (!path.is_empty()).then_some(Self { base_id, path })
+    }
+
+    async fn resolve(&self, folders: &FolderPathCache) -> Result<String, GopenerError> {
+        let client = GoogleClient::new().await?;
+        folders
+            .resolve(&client, self.base_id.as_deref(), &self.path)
+            .await
+    }
+}
 
-/// Upload a file to Google Drive with conversion
+#[derive(Debug, Serialize, Clone)]
+pub struct BatchUploadResult {
+    pub batch_id: String,
+    pub files: Vec<FileUploadOutcome>,
+}
+
+/// Upload a file to Google Drive with conversion.
+///
+/// `folder_path` such as `Clients/Acme/2026` is resolved below `folder_id`, or
+/// below My Drive without one, creating any folder that does not exist yet.
+/// `on_existing` decides what happens when the file was uploaded before.
+/// `convert: false` keeps the file in its original format; without it the
+/// per-extension default from Settings applies. `ocr_language` such as `de`
+/// helps Drive read the text of PDFs and images, overriding the Settings default.
+/// A caller-chosen `job_id` lets the caller follow and cancel the upload from
+/// the moment it is registered, without guessing which progress events are its own.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn upload_file(
     app: tauri::AppHandle,
+    manager: State<'_, UploadManager>,
+    folders: State<'_, FolderPathCache>,
     file_path: String,
     folder_id: Option<String>,
-) -> Result<UploadResult, String> {
+    folder_path: Option<String>,
+    on_existing: Option<ExistingFileMode>,
+    convert: Option<bool>,
+    ocr_language: Option<String>,
+    job_id: Option<String>,
+) -> Result<UploadResult, GopenerError> {
+    let settings = get_settings().await?;
+    let ocr_language = resolve_ocr_language(ocr_language, &settings)?;
+    let manager = manager.inner().clone();
+    let job_id = match job_id {
+        Some(id) => manager.create_with_id(id, &file_path, folder_id.clone())?,
+        None => manager.create(&file_path, folder_id.clone()),
+    };
+
+    // A cancel while the path resolves is seen as soon as the job runs
+    let resolved_from = FolderPathSource::new(folder_id.clone(), folder_path);
+    let folder_id = match resolve_destination(&folders, folder_id, resolved_from.as_ref()).await {
+        Ok(folder_id) => folder_id,
+        Err(e) => {
+            manager.fail(&job_id, &e);
+            return Err(e);
+        }
+    };
+    manager.set_folder(&job_id, folder_id.clone());
+    let item = BatchItem {
+        convert: convert.unwrap_or_else(|| settings.converts(Path::new(&file_path))),
+        file_path,
+        folder_id,
+        ocr_language,
+        on_existing: on_existing.unwrap_or_default(),
+        resolved_from,
+    };
+    run_upload_job(app, manager, job_id, item).await
+}
+
+/// Upload several files, a few at a time.
+///
+/// A failing file does not stop the batch; every file gets its own outcome in
+/// the order the paths were given.
+#[tauri::command]
+pub async fn upload_files(
+    app: tauri::AppHandle,
+    manager: State<'_, UploadManager>,
+    folders: State<'_, FolderPathCache>,
+    paths: Vec<String>,
+    folder_id: Option<String>,
+    folder_path: Option<String>,
+    options: Option<UploadOptions>,
+) -> Result<BatchUploadResult, GopenerError> {
+    let resolved_from = FolderPathSource::new(folder_id.clone(), folder_path);
+    let folder_id = resolve_destination(&folders, folder_id, resolved_from.as_ref()).await?;
+    let options = options.unwrap_or_default();
+    let settings = get_settings().await?;
+    let ocr_language = resolve_ocr_language(options.ocr_language.clone(), &settings)?;
+    let items = paths
+        .into_iter()
+        .map(|file_path| BatchItem {
+            convert: options
+                .convert
+                .unwrap_or_else(|| settings.converts(Path::new(&file_path))),
+            file_path,
+            folder_id: folder_id.clone(),
+            ocr_language: ocr_language.clone(),
+            on_existing: options.on_existing,
+            resolved_from: resolved_from.clone(),
+        })
+        .collect();
+
+    Ok(run_batch(app, manager.inner().clone(), items, options.concurrency()).await)
+}
+
+/// OCR language to use: the requested one, else the Settings default, if any.
+///
+/// Drive takes ISO 639 codes such as `en`, optionally with a region as in `pt-BR`.
+pub fn resolve_ocr_language(
+    requested: Option<String>,
+    settings: &Settings,
+) -> Result<Option<String>, GopenerError> {
+    let Some(language) = requested
+        .or_else(|| settings.ocr_language.clone())
+        .map(|language| language.trim().to_string())
+        .filter(|language| !language.is_empty())
+    else {
+        return Ok(None);
+    };
+
+    let (code, region) = match language.split_once('-') {
+        Some((code, region)) => (code, Some(region)),
+        None => (language.as_str(), None),
+    };
+    let valid_code = (2..=3).contains(&code.len()) && code.chars().all(|c| c.is_ascii_alphabetic());
+    let valid_region = region
+        .iter()
+        .all(|region| !region.is_empty() && region.chars().all(|c| c.is_ascii_alphanumeric()));
+    if !valid_code || !valid_region {
+        return Err(GopenerError::InvalidInput(format!(
+            "Not a language code: {}",
+            language
+        )));
+    }
+    Ok(Some(language))
+}
+
+/// Folder to upload into: the one at `path` when given, otherwise `folder_id`
+async fn resolve_destination(
+    folders: &FolderPathCache,
+    folder_id: Option<String>,
+    path: Option<&FolderPathSource>,
+) -> Result<Option<String>, GopenerError> {
+    match path {
+        Some(path) => path.resolve(folders).await.map(Some),
+        None => Ok(folder_id),
+    }
+}
+
+/// Run a batch of uploads with at most `concurrency` in flight, emitting batch progress
+pub async fn run_batch(
+    app: tauri::AppHandle,
+    manager: UploadManager,
+    items: Vec<BatchItem>,
+    concurrency: usize,
+) -> BatchUploadResult {
+    let files: Vec<(String, Option<String>)> = items
+        .iter()
+        .map(|item| (item.file_path.clone(), item.folder_id.clone()))
+        .collect();
+    let (batch_id, job_ids) = manager.create_batch(&files);
+
+    // Known sizes up front keep the batch percentage steady as files start
+    for (job_id, item) in job_ids.iter().zip(&items) {
+        if let Ok(metadata) = std::fs::metadata(&item.file_path) {
+            let mut progress = UploadProgress::empty(job_id);
+            progress.total_bytes = metadata.len();
+            manager.set_progress(job_id, progress);
+        }
+    }
+
+    // Owned items keep the stream future Send, as Tauri requires for async commands
+    let work: Vec<(usize, String, BatchItem)> = job_ids
+        .iter()
+        .cloned()
+        .zip(items)
+        .enumerate()
+        .map(|(index, (job_id, item))| (index, job_id, item))
+        .collect();
+    // Unordered, so a slow file doesn't hold back the next ones from starting
+    let uploads = futures_util::stream::iter(work)
+        .map(|(index, job_id, item)| {
+            let upload = run_upload_job(app.clone(), manager.clone(), job_id, item);
+            async move { (index, upload.await) }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect::<Vec<_>>();
+    tokio::pin!(uploads);
+
+    let emit_progress = || {
+        let jobs: Vec<UploadJob> = job_ids.iter().filter_map(|id| manager.get(id)).collect();
+        let _ = app.emit("upload-batch-progress", batch_progress(&batch_id, &jobs));
+    };
+
+    let mut ticker = tokio::time::interval(PROGRESS_EVENT_INTERVAL);
+    let mut results = loop {
+        tokio::select! {
+            results = &mut uploads => break results,
+            _ = ticker.tick() => emit_progress(),
+        }
+    };
+    emit_progress();
+
+    // Results arrive as uploads finish; outcomes follow the order the files were given
+    results.sort_by_key(|(index, _)| *index);
+    let results = results.into_iter().map(|(_, result)| result);
+    let files = job_ids
+        .into_iter()
+        .zip(files)
+        .zip(results)
+        .map(|((job_id, (file_path, _)), result)| {
+            let (state, result, error) = match result {
+                Ok(result) => (JobState::Succeeded, Some(result), None),
+                Err(GopenerError::Cancelled(_)) => (JobState::Cancelled, None, None),
+                Err(e) => (JobState::Failed, None, Some(e)),
+            };
+            FileUploadOutcome {
+                job_id,
+                file_path,
+                state,
+                result,
+                error,
+            }
+        })
+        .collect();
+
+    BatchUploadResult { batch_id, files }
+}
+
+/// Aggregate the progress of all jobs in a batch
+fn batch_progress(batch_id: &str, jobs: &[UploadJob]) -> BatchProgress {
+    let count = |state: JobState| jobs.iter().filter(|job| job.state == state).count();
+    let bytes_uploaded: u64 = jobs.iter().map(|job| job.progress.bytes_uploaded).sum();
+    let total_bytes: u64 = jobs.iter().map(|job| job.progress.total_bytes).sum();
+    let finished = jobs.iter().filter(|job| job.state.is_finished()).count();
+
+    let percentage = if total_bytes > 0 {
+        (bytes_uploaded as f64 / total_bytes as f64) * 100.0
+    } else if !jobs.is_empty() {
+        (finished as f64 / jobs.len() as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    BatchProgress {
+        batch_id: batch_id.to_string(),
+        total_files: jobs.len(),
+        succeeded: count(JobState::Succeeded),
+        failed: count(JobState::Failed),
+        cancelled: count(JobState::Cancelled),
+        bytes_uploaded,
+        total_bytes,
+        percentage,
+    }
+}
+
+/// Run a registered upload job to completion and record its outcome
+async fn run_upload_job(
+    app: tauri::AppHandle,
+    manager: UploadManager,
+    job_id: String,
+    item: BatchItem,
+) -> Result<UploadResult, GopenerError> {
+    let Some(mut cancel_signal) = manager.cancel_signal(&job_id) else {
+        return Err(GopenerError::JobNotFound(job_id));
+    };
+    manager.start(&job_id);
+
+    let work = perform_upload(&app, &manager, &job_id, item);
+    match until_cancelled(&mut cancel_signal, work).await {
+        Some(Ok(result)) => {
+            manager.succeed(&job_id, result.clone());
+            Ok(result)
+        }
+        Some(Err(e)) => {
+            manager.fail(&job_id, &e);
+            Err(e)
+        }
+        None => {
+            // The request was dropped mid-flight; also discard what Drive kept of it
+            if let Some(session_url) = manager.session_url(&job_id) {
+                let _ =
+                    upload::cancel_resumable_session(&reqwest::Client::new(), &session_url).await;
+            }
+            manager.mark_cancelled(&job_id);
+            let _ = app.emit(
+                "upload-cancelled",
+                UploadCancelled {
+                    job_id: job_id.clone(),
+                },
+            );
+            Err(GopenerError::Cancelled(UPLOAD_CANCELLED.to_string()))
+        }
+    }
+}
+
+async fn perform_upload(
+    app: &tauri::AppHandle,
+    manager: &UploadManager,
+    job_id: &str,
+    item: BatchItem,
+) -> Result<UploadResult, GopenerError> {
+    let BatchItem {
+        file_path,
+        mut folder_id,
+        convert,
+        ocr_language,
+        on_existing,
+        resolved_from,
+    } = item;
     let path = Path::new(&file_path);
 
     // Validate file exists
     if !path.exists() {
-        return Err("File does not exist".to_string());
+        return Err(GopenerError::FileNotFound(file_path));
     }
 
     // Get file info
-    let file_info = get_file_info(path).map_err(|e| format!("Failed to get file info: {}", e))?;
+    let file_info = get_file_info(path)
+        .map_err(|e| GopenerError::Io(format!("Failed to get file info: {}", e)))?;
+
+    // The content decides the format, so a renamed file isn't sent under the wrong type.
+    // Drive can't open a password-protected file, so converting it could only fail.
+    let (detected, encrypted) = {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let detected = sniff_format(&path)?;
+            let encrypted = convert && is_encrypted(&path)?;
+            Ok::<_, std::io::Error>((detected, encrypted))
+        })
+        .await
+        .map_err(|e| GopenerError::Internal(format!("Failed to read file: {}", e)))?
+        .map_err(|e| GopenerError::Io(format!("Failed to read file: {}", e)))?
+    };
+    if encrypted {
+        return Err(GopenerError::PasswordProtected(file_info.name.clone()));
+    }
+
+    // A file that can't be identified never leaves the machine for conversion
+    let format = if convert {
+        let detected = detected.ok_or_else(|| match detect_file_type(path) {
+            Some(_) => GopenerError::UnrecognizedContent(file_info.name.clone()),
+            None => {
+                let extension = path.extension().unwrap_or_default();
+                GopenerError::UnsupportedFileType(extension.to_string_lossy().to_string())
+            }
+        })?;
+        Some(detected.format)
+    } else {
+        None
+    };
+    let google_type = format.map(|format| format.target);
+
+    // Drive refuses conversions over its limits, so find out before a long upload
+    let mut warnings: Vec<String> = detected.and_then(|d| d.warning(path)).into_iter().collect();
+    if let Some(format) = format {
+        let path = path.to_path_buf();
+        let checked = tokio::task::spawn_blocking(move || preflight(&path, format))
+            .await
+            .map_err(|e| GopenerError::Internal(format!("Failed to check file: {}", e)))?
+            .map_err(|e| GopenerError::Io(format!("Failed to read file: {}", e)))?;
+        match checked {
+            Preflight::Fits => {}
+            Preflight::NearLimit(warning) => warnings.push(warning),
+            Preflight::OverLimit(problem) => return Err(GopenerError::ConversionLimit(problem)),
+        }
+    }
+
+    // An earlier upload of this file decides whether to add a revision or ask first
+    let local_path = std::fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| file_path.clone());
+    let content_hash = {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || content_hash(&path))
+            .await
+            .map_err(|e| GopenerError::Internal(format!("Failed to hash file: {}", e)))?
+            .map_err(|e| GopenerError::Io(format!("Failed to read file: {}", e)))?
+    };
+    let history = app.state::<UploadHistory>().inner().clone();
+    let mut existing = match on_existing {
+        ExistingFileMode::Create => None,
+        ExistingFileMode::Update | ExistingFileMode::Ask => {
+            let (local_path, content_hash) = (local_path.clone(), content_hash.clone());
+            // Without the history the file can still go up, only as a new copy
+            match history
+                .with(move |db| db.find(&local_path, &content_hash))
+                .await
+            {
+                Ok(record) => record,
+                Err(e) => {
+                    warnings.push(format!(
+                        "Earlier uploads could not be looked up, so this was uploaded \
+                         as a new file: {}",
+                        e
+                    ));
+                    None
+                }
+            }
+        }
+    };
 
-    // Detect file type for conversion
-    let google_type = detect_file_type(path).ok_or("Unsupported file type")?;
+    // A revision can't change whether the file is converted, so a different
+    // choice than last time makes a new file
+    let file_mime = detected
+        .map(|d| d.format.mime_type.to_string())
+        .unwrap_or_else(|| get_mime_type(path));
+    let drive_mime = google_type.map_or(file_mime.as_str(), |t| t.google_mime_type());
+    let other_format = existing
+        .as_ref()
+        .and_then(|record| record.mime_type.as_deref())
+        .is_some_and(|mime_type| mime_type != drive_mime);
+    if other_format {
+        existing = None;
+        warnings.push(format!(
+            "{} was uploaded before in another format, so this was uploaded as a new file",
+            file_info.name
+        ));
+    }
+    if let (Some(record), ExistingFileMode::Ask) = (&existing, on_existing) {
+        return Err(GopenerError::AlreadyUploaded(record.name.clone()));
+    }
 
     // Get valid access token
     let access_token = get_valid_token().await?;
 
-    // Read file contents
-    let file_contents =
-        std::fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let total_size = file_info.size;
 
-    let total_size = file_contents.len() as u64;
-    UPLOAD_TOTAL.store(total_size, Ordering::SeqCst);
-    UPLOAD_PROGRESS.store(0, Ordering::SeqCst);
+    // Every chunk of the request body reports here; the tracker decides when to emit
+    let tracker = Arc::new(Mutex::new(ProgressTracker::new(
+        total_size,
+        PROGRESS_EVENT_INTERVAL,
+    )));
+    let on_progress: ProgressCallback = {
+        let app = app.clone();
+        let manager = manager.clone();
+        let job_id = job_id.to_string();
+        let tracker = tracker.clone();
+        Arc::new(move |sent| {
+            let mut tracker = tracker.lock().unwrap();
+            let due = tracker.record(sent, Instant::now());
+            let progress = UploadProgress::from_tracker(&job_id, &tracker);
+            manager.set_progress(&job_id, progress.clone());
+            if due {
+                let _ = app.emit("upload-progress", progress);
+            }
+        })
+    };
 
     // Emit initial progress
-    let _ = app.emit(
-        "upload-progress",
-        UploadProgress {
-            bytes_uploaded: 0,
-            total_bytes: total_size,
-            percentage: 0.0,
-        },
-    );
+    on_progress(0);
 
     // Build metadata
-    let mut metadata = serde_json::json!({
+    let mut new_file_metadata = serde_json::json!({
         "name": file_info.name,
-        "mimeType": google_type.google_mime_type(),
     });
 
+    // Without a target mimeType Drive stores the file as uploaded
+    if let Some(google_type) = google_type {
+        new_file_metadata["mimeType"] = serde_json::json!(google_type.google_mime_type());
+    }
+
     // Add parent folder if specified
     if let Some(ref folder) = folder_id {
-        metadata["parents"] = serde_json::json!([folder]);
+        new_file_metadata["parents"] = serde_json::json!([folder]);
     }
 
-    // Create multipart form
-    let metadata_json = serde_json::to_string(&metadata).map_err(|e| e.to_string())?;
+    // Only a conversion of a scan reads text from it
+    let ocr_language =
+        ocr_language.filter(|_| google_type.is_some() && detected.is_some_and(|d| d.format.ocr));
+
+    let client = reqwest::Client::new();
+    let resumable = ResumableConfig {
+        retry: get_settings().await?.retry_policy(),
+        ..ResumableConfig::default()
+    };
 
-    let file_mime = get_mime_type(path);
+    // A folder resolved from a path may have been deleted since it was cached
+    let mut resolved_again = false;
+    let drive_file = loop {
+        // A revision keeps the file's name, type and folder; only the content changes
+        let (target, metadata) = match &existing {
+            Some(record) => (
+                UploadTarget::Revision(&record.drive_id),
+                serde_json::json!({}),
+            ),
+            None => (UploadTarget::NewFile, new_file_metadata.clone()),
+        };
 
-    // Build multipart body manually for Google Drive API
-    let boundary = "gopener_boundary_12345";
-    let mut body = Vec::new();
+        // Large files go through a resumable session so a network blip doesn't restart the upload
+        let transfer = async {
+            if total_size >= upload::RESUMABLE_THRESHOLD {
+                let session_url = upload::start_resumable_session(
+                    &client,
+                    config::GOOGLE_DRIVE_UPLOAD_URL,
+                    target,
+                    &access_token,
+                    &metadata,
+                    ocr_language.as_deref(),
+                    &file_mime,
+                    total_size,
+                    &resumable.retry,
+                )
+                .await?;
+                manager.set_session_url(job_id, &session_url);
 
-    // Metadata part
-    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-    body.extend_from_slice(b"Content-Type: application/json; charset=UTF-8\r\n\r\n");
-    body.extend_from_slice(metadata_json.as_bytes());
-    body.extend_from_slice(b"\r\n");
+                upload::upload_resumable(
+                    &client,
+                    &session_url,
+                    path,
+                    total_size,
+                    &resumable,
+                    on_progress.clone(),
+                )
+                .await
+            } else {
+                upload::upload_multipart(
+                    &client,
+                    config::GOOGLE_DRIVE_UPLOAD_URL,
+                    target,
+                    &access_token,
+                    &metadata,
+                    ocr_language.as_deref(),
+                    path,
+                    &file_mime,
+                    &resumable.retry,
+                    on_progress.clone(),
+                )
+                .await
+            }
+        };
+        let result = transfer.await;
 
-    // File part
-    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-    body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", file_mime).as_bytes());
-    body.extend_from_slice(&file_contents);
-    body.extend_from_slice(b"\r\n");
+        match (result, existing.take()) {
+            // The earlier copy was deleted from Drive, so upload a new file instead
+            (Err(e), Some(record)) if e.code() == "not_found" => {
+                let drive_id = record.drive_id;
+                if let Err(e) = history.with(move |db| db.forget(&drive_id)).await {
+                    warnings.push(format!(
+                        "The deleted Drive file could not be removed from the history: {}",
+                        e
+                    ));
+                }
+            }
+            // The cached destination folder is gone, so look its path up again, once
+            (Err(e), None) if e.code() == "not_found" && !resolved_again => {
+                let Some(source) = &resolved_from else {
+                    return Err(e);
+                };
+                resolved_again = true;
+                let folders = app.state::<FolderPathCache>();
+                folders.evict(source.base_id.as_deref(), &source.path).await;
+                let folder = source.resolve(&folders).await?;
+                manager.set_folder(job_id, Some(folder.clone()));
+                new_file_metadata["parents"] = serde_json::json!([folder]);
+                folder_id = Some(folder);
+            }
+            (result, record) => {
+                existing = record;
+                break result?;
+            }
+        }
+    };
 
-    // End boundary
-    body.extend_from_slice(format!("--{}--", boundary).as_bytes());
+    // Make sure the UI sees 100% even if the last piece landed inside the throttle window
+    on_progress(total_size);
 
-    let client = reqwest::Client::new();
+    // What Drive made of the file, rather than what was asked for
+    let converted_to = GoogleFileType::from_google_mime_type(&drive_file.mime_type);
+    let mut result = UploadResult {
+        file_id: drive_file.id.clone(),
+        name: drive_file.name.clone(),
+        web_view_link: drive_file.web_view_link.clone(),
+        file_type: converted_to
+            .map(|t| t.display_name())
+            .or_else(|| detected.map(|d| d.format.name))
+            .or_else(|| original_format_name(path))
+            .unwrap_or(UNCONVERTED_FILE_TYPE)
+            .to_string(),
+        converted: converted_to.is_some(),
+        updated_existing: existing.is_some(),
+        warning: None,
+    };
 
-    let response = client
-        .post(format!("{}?uploadType=multipart&fields=id,name,webViewLink,mimeType", config::GOOGLE_DRIVE_UPLOAD_URL))
-        .header("Authorization", format!("Bearer {}", access_token))
-        .header("Content-Type", format!("multipart/related; boundary={}", boundary))
-        .body(body)
-        .send()
-        .await
-        .map_err(|e| format!("Upload failed: {}", e))?;
-
-    // Update progress to 100%
-    UPLOAD_PROGRESS.store(total_size, Ordering::SeqCst);
-    let _ = app.emit(
-        "upload-progress",
-        UploadProgress {
-            bytes_uploaded: total_size,
-            total_bytes: total_size,
-            percentage: 100.0,
+    let uploaded_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let modified_at = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64);
+    let record = UploadRecord {
+        drive_id: result.file_id.clone(),
+        local_path,
+        content_hash: Some(content_hash),
+        size: Some(total_size),
+        modified_at,
+        name: result.name.clone(),
+        mime_type: Some(drive_file.mime_type),
+        file_type: result.file_type.clone(),
+        web_view_link: result.web_view_link.clone(),
+        // A revision stays in the folder the file already lives in
+        folder_id: if result.updated_existing {
+            None
+        } else {
+            folder_id
         },
-    );
+        first_uploaded_at: uploaded_at,
+        last_uploaded_at: uploaded_at,
+    };
+    // The upload itself worked, so a history failure only warns
+    if let Err(e) = history.with(move |db| db.remember(&record)).await {
+        warnings.push(format!(
+            "The upload could not be saved to the history, so uploading this file \
+             again will make a new copy: {}",
+            e
+        ));
+    }
+    result.warning = (!warnings.is_empty()).then(|| warnings.join("; "));
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("Upload failed: {}", error_text));
+    fn job(id: &str, state: JobState, uploaded: u64, total: u64) -> UploadJob {
+        let mut progress = UploadProgress::empty(id);
+        progress.bytes_uploaded = uploaded;
+        progress.total_bytes = total;
+        UploadJob {
+            id: id.to_string(),
+            batch_id: Some("batch-1".to_string()),
+            file_path: format!("/tmp/{}.xlsx", id),
+            folder_id: None,
+            state,
+            progress,
+            result: None,
+            error: None,
+        }
     }
 
-    #[derive(Deserialize)]
-    struct DriveFile {
-        id: String,
-        name: String,
-        #[serde(rename = "webViewLink")]
-        web_view_link: String,
-        #[serde(rename = "mimeType")]
-        _mime_type: String,
+    #[test]
+    fn test_batch_progress_sums_jobs() {
+        let jobs = vec![
+            job("upload-1", JobState::Succeeded, 100, 100),
+            job("upload-2", JobState::Running, 50, 200),
+            job("upload-3", JobState::Failed, 0, 100),
+            job("upload-4", JobState::Queued, 0, 0),
+        ];
+
+        let progress = batch_progress("batch-1", &jobs);
+
+        assert_eq!(progress.total_files, 4);
+        assert_eq!(progress.succeeded, 1);
+        assert_eq!(progress.failed, 1);
+        assert_eq!(progress.cancelled, 0);
+        assert_eq!(progress.bytes_uploaded, 150);
+        assert_eq!(progress.total_bytes, 400);
+        assert_eq!(progress.percentage, 37.5);
     }
 
-    let drive_file: DriveFile = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    #[test]
+    fn test_batch_progress_of_empty_files_counts_finished_jobs() {
+        let jobs = vec![
+            job("upload-1", JobState::Succeeded, 0, 0),
+            job("upload-2", JobState::Queued, 0, 0),
+        ];
+        assert_eq!(batch_progress("batch-1", &jobs).percentage, 50.0);
+        assert_eq!(batch_progress("batch-1", &[]).percentage, 0.0);
+    }
 
-    Ok(UploadResult {
-        file_id: drive_file.id,
-        name: drive_file.name,
-        web_view_link: drive_file.web_view_link,
-        file_type: google_type.display_name().to_string(),
-    })
-}
+    #[test]
+    fn test_upload_options_concurrency() {
+        assert_eq!(UploadOptions::default().concurrency(), DEFAULT_CONCURRENCY);
+        let options = |n| UploadOptions {
+            concurrency: Some(n),
+            ..Default::default()
+        };
+        assert_eq!(options(0).concurrency(), 1);
+        assert_eq!(options(5).concurrency(), 5);
+        assert_eq!(options(100).concurrency(), MAX_CONCURRENCY);
+    }
 
-/// Get current upload progress
-#[tauri::command]
-pub async fn get_upload_progress() -> UploadProgress {
-    let uploaded = UPLOAD_PROGRESS.load(Ordering::SeqCst);
-    let total = UPLOAD_TOTAL.load(Ordering::SeqCst);
-    let percentage = if total > 0 {
-        (uploaded as f64 / total as f64) * 100.0
-    } else {
-        0.0
-    };
+    #[test]
+    fn test_upload_options_deserialize_with_defaults() {
+        let options: UploadOptions = serde_json::from_str("{}").unwrap();
+        assert!(options.concurrency.is_none());
+        assert_eq!(options.on_existing, ExistingFileMode::Ask);
+        let options: UploadOptions = serde_json::from_str(r#"{"concurrency": 4}"#).unwrap();
+        assert_eq!(options.concurrency(), 4);
+        let options: UploadOptions = serde_json::from_str(r#"{"on_existing": "update"}"#).unwrap();
+        assert_eq!(options.on_existing, ExistingFileMode::Update);
+    }
 
-    UploadProgress {
-        bytes_uploaded: uploaded,
-        total_bytes: total,
-        percentage,
+    #[test]
+    fn test_resolve_ocr_language() {
+        let mut settings = Settings::new();
+        assert_eq!(resolve_ocr_language(None, &settings).unwrap(), None);
+
+        settings.ocr_language = Some("de".to_string());
+        assert_eq!(
+            resolve_ocr_language(None, &settings).unwrap().as_deref(),
+            Some("de")
+        );
+        assert_eq!(
+            resolve_ocr_language(Some(" pt-BR ".to_string()), &settings)
+                .unwrap()
+                .as_deref(),
+            Some("pt-BR")
+        );
+
+        for invalid in ["english", "e", "en&x=1", "en-"] {
+            let error = resolve_ocr_language(Some(invalid.to_string()), &settings).unwrap_err();
+            assert_eq!(error.code(), "invalid_input", "{}", invalid);
+        }
     }
 }
@@ -0,0 +1,541 @@
+//! Permanent record of which local files were uploaded to which Drive files.
+//!
+//! Kept in `uploads.db`, an SQLite database in the config dir with one row per
+//! Drive file. Uploads look up earlier copies here by local path, then by
+//! content hash so a moved or renamed file still finds its Drive copy. A copy
+//! whose local file still exists belongs to that file, not to its duplicates.
+
+use crate::commands::storage::{app_config_dir, Settings};
+use crate::error::GopenerError;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::State;
+
+/// How long a write waits for another upload writing at the same time
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Schema changes, applied in order; `user_version` counts the ones already applied
+const MIGRATIONS: &[&str] = &["
+    CREATE TABLE uploads (
+        drive_id TEXT PRIMARY KEY,
+        local_path TEXT NOT NULL,
+        content_hash TEXT,
+        size INTEGER,
+        modified_at INTEGER,
+        name TEXT NOT NULL,
+        mime_type TEXT,
+        file_type TEXT NOT NULL,
+        web_view_link TEXT NOT NULL,
+        folder_id TEXT,
+        first_uploaded_at INTEGER NOT NULL,
+        last_uploaded_at INTEGER NOT NULL
+    );
+    CREATE INDEX uploads_local_path ON uploads (local_path);
+    CREATE INDEX uploads_content_hash ON uploads (content_hash);
+    CREATE INDEX uploads_folder_id ON uploads (folder_id);
+"];
+
+const COLUMNS: &str = "drive_id, local_path, content_hash, size, modified_at, name, mime_type, \
+                       file_type, web_view_link, folder_id, first_uploaded_at, last_uploaded_at";
+
+/// A Drive file and the local file it was last uploaded from.
+///
+/// Times are Unix milliseconds. Fields are `None` for uploads imported from
+/// before they were recorded.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UploadRecord {
+    pub drive_id: String,
+    pub local_path: String,
+    /// Hex SHA-256 of the content last uploaded
+    pub content_hash: Option<String>,
+    pub size: Option<u64>,
+    /// Modification time of the local file when it was uploaded
+    pub modified_at: Option<u64>,
+    pub name: String,
+    pub mime_type: Option<String>,
+    pub file_type: String,
+    pub web_view_link: String,
+    pub folder_id: Option<String>,
+    pub first_uploaded_at: u64,
+    pub last_uploaded_at: u64,
+}
+
+impl UploadRecord {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            drive_id: row.get(0)?,
+            local_path: row.get(1)?,
+            content_hash: row.get(2)?,
+            size: row.get(3)?,
+            modified_at: row.get(4)?,
+            name: row.get(5)?,
+            mime_type: row.get(6)?,
+            file_type: row.get(7)?,
+            web_view_link: row.get(8)?,
+            folder_id: row.get(9)?,
+            first_uploaded_at: row.get(10)?,
+            last_uploaded_at: row.get(11)?,
+        })
+    }
+}
+
+pub struct UploadDb {
+    conn: Connection,
+}
+
+/// One connection to `uploads.db` shared by every upload and query, opened on
+/// first use so a config dir problem only fails what needs the history
+#[derive(Clone, Default)]
+pub struct UploadHistory {
+    db: Arc<Mutex<Option<UploadDb>>>,
+}
+
+impl UploadHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `query` on the shared connection off the async runtime, opening
+    /// the database if needed
+    pub async fn with<T, F>(&self, query: F) -> Result<T, GopenerError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&UploadDb) -> Result<T, GopenerError> + Send + 'static,
+    {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut db = db.lock().unwrap();
+            let db = match &mut *db {
+                Some(db) => db,
+                None => db.insert(UploadDb::open()?),
+            };
+            query(db)
+        })
+        .await
+        .map_err(|e| GopenerError::Internal(format!("Upload history failed: {}", e)))?
+    }
+}
+
+impl UploadDb {
+    /// Open `uploads.db` in the config dir, creating and migrating it as needed
+    pub fn open() -> Result<Self, GopenerError> {
+        let dir = app_config_dir()?;
+        let conn = Connection::open(dir.join("uploads.db")).map_err(db_error)?;
+        Self::with_connection(conn, Some(&dir))
+    }
+
+    /// Migrate `conn`, importing earlier upload history from `legacy_dir` on creation
+    fn with_connection(conn: Connection, legacy_dir: Option<&Path>) -> Result<Self, GopenerError> {
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(db_error)?;
+        let mut db = Self { conn };
+        let created = db.migrate()?;
+        if let (true, Some(dir)) = (created, legacy_dir) {
+            db.import_recent_files(dir)?;
+        }
+        Ok(db)
+    }
+
+    /// Apply pending migrations; true when the database was just created.
+    ///
+    /// A database migrated by a newer Gopener is refused rather than downgraded.
+    fn migrate(&mut self) -> Result<bool, GopenerError> {
+        let version: usize = self
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(db_error)?;
+        if version > MIGRATIONS.len() {
+            return Err(GopenerError::Io(
+                "The upload history was created by a newer version of Gopener".to_string(),
+            ));
+        }
+        if version == MIGRATIONS.len() {
+            return Ok(false);
+        }
+
+        let tx = self.conn.transaction().map_err(db_error)?;
+        for migration in MIGRATIONS.iter().skip(version) {
+            tx.execute_batch(migration).map_err(db_error)?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len())
+            .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+
+        Ok(version == 0)
+    }
+
+    /// Import the recent files list from `settings.json`
+    fn import_recent_files(&mut self, dir: &Path) -> Result<(), GopenerError> {
+        let Some(settings) = read_json::<Settings>(&dir.join("settings.json")) else {
+            return Ok(());
+        };
+        for file in settings.recent_files {
+            self.remember(&UploadRecord {
+                drive_id: file.id,
+                local_path: file.path,
+                content_hash: None,
+                size: None,
+                modified_at: None,
+                name: file.name,
+                mime_type: None,
+                file_type: file.file_type,
+                web_view_link: file.google_url,
+                folder_id: None,
+                first_uploaded_at: file.uploaded_at,
+                last_uploaded_at: file.uploaded_at,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Add or refresh the row for `record.drive_id`, keeping when it was first uploaded
+    pub fn remember(&self, record: &UploadRecord) -> Result<(), GopenerError> {
+        self.conn
+            .execute(
+                &format!(
+                    "INSERT INTO uploads ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                     ON CONFLICT (drive_id) DO UPDATE SET
+                        local_path = excluded.local_path,
+                        content_hash = COALESCE(excluded.content_hash, content_hash),
+                        size = COALESCE(excluded.size, size),
+                        modified_at = COALESCE(excluded.modified_at, modified_at),
+                        name = excluded.name,
+                        mime_type = COALESCE(excluded.mime_type, mime_type),
+                        file_type = excluded.file_type,
+                        web_view_link = excluded.web_view_link,
+                        folder_id = COALESCE(excluded.folder_id, folder_id),
+                        first_uploaded_at = MIN(first_uploaded_at, excluded.first_uploaded_at),
+                        last_uploaded_at = MAX(last_uploaded_at, excluded.last_uploaded_at)",
+                    COLUMNS
+                ),
+                params![
+                    record.drive_id,
+                    record.local_path,
+                    record.content_hash,
+                    record.size,
+                    record.modified_at,
+                    record.name,
+                    record.mime_type,
+                    record.file_type,
+                    record.web_view_link,
+                    record.folder_id,
+                    record.first_uploaded_at,
+                    record.last_uploaded_at,
+                ],
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Latest upload of `local_path`, or else of a file with the same content
+    /// that is no longer where it was uploaded from, i.e. was moved or renamed
+    pub fn find(
+        &self,
+        local_path: &str,
+        content_hash: &str,
+    ) -> Result<Option<UploadRecord>, GopenerError> {
+        if let Some(record) = self.latest("local_path", local_path)? {
+            return Ok(Some(record));
+        }
+        // `remember` moves the row to the new path, so a live original would
+        // lose its Drive copy to the duplicate
+        Ok(self
+            .select("content_hash", content_hash)?
+            .into_iter()
+            .find(|record| !Path::new(&record.local_path).exists()))
+    }
+
+    /// Uploads of `local_path`, most recent first
+    pub fn by_path(&self, local_path: &str) -> Result<Vec<UploadRecord>, GopenerError> {
+        self.select("local_path", local_path)
+    }
+
+    pub fn by_drive_id(&self, drive_id: &str) -> Result<Option<UploadRecord>, GopenerError> {
+        self.latest("drive_id", drive_id)
+    }
+
+    /// Uploads into the Drive folder `folder_id`, most recent first
+    pub fn in_folder(&self, folder_id: &str) -> Result<Vec<UploadRecord>, GopenerError> {
+        self.select("folder_id", folder_id)
+    }
+
+    /// Drop the row for a Drive file, e.g. after it turned out to be deleted
+    pub fn forget(&self, drive_id: &str) -> Result<(), GopenerError> {
+        self.conn
+            .execute("DELETE FROM uploads WHERE drive_id = ?1", [drive_id])
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    fn latest(&self, column: &str, value: &str) -> Result<Option<UploadRecord>, GopenerError> {
+        self.conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM uploads WHERE {} = ?1 ORDER BY last_uploaded_at DESC LIMIT 1",
+                    COLUMNS, column
+                ),
+                [value],
+                UploadRecord::from_row,
+            )
+            .optional()
+            .map_err(db_error)
+    }
+
+    fn select(&self, column: &str, value: &str) -> Result<Vec<UploadRecord>, GopenerError> {
+        let mut statement = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM uploads WHERE {} = ?1 ORDER BY last_uploaded_at DESC",
+                COLUMNS, column
+            ))
+            .map_err(db_error)?;
+        let records = statement
+            .query_map([value], UploadRecord::from_row)
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+        Ok(records)
+    }
+}
+
+fn db_error(e: rusqlite::Error) -> GopenerError {
+    GopenerError::Io(format!("Upload database error: {}", e))
+}
+
+/// Contents of a JSON file, or `None` when it is missing or unreadable
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Uploads made from a local file, most recent first
+#[tauri::command]
+pub async fn find_uploads_by_path(
+    history: State<'_, UploadHistory>,
+    local_path: String,
+) -> Result<Vec<UploadRecord>, GopenerError> {
+    let local_path = fs::canonicalize(&local_path)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or(local_path);
+    history.with(move |db| db.by_path(&local_path)).await
+}
+
+/// Where a Drive file was uploaded from, if Gopener uploaded it
+#[tauri::command]
+pub async fn find_upload_by_drive_id(
+    history: State<'_, UploadHistory>,
+    drive_id: String,
+) -> Result<Option<UploadRecord>, GopenerError> {
+    history.with(move |db| db.by_drive_id(&drive_id)).await
+}
+
+/// Files uploaded into a Drive folder, most recent first
+#[tauri::command]
+pub async fn list_uploads_in_folder(
+    history: State<'_, UploadHistory>,
+    folder_id: String,
+) -> Result<Vec<UploadRecord>, GopenerError> {
+    history.with(move |db| db.in_folder(&folder_id)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_db() -> UploadDb {
+        UploadDb::with_connection(Connection::open_in_memory().unwrap(), None).unwrap()
+    }
+
+    fn record(drive_id: &str, local_path: &str, uploaded_at: u64) -> UploadRecord {
+        UploadRecord {
+            drive_id: drive_id.to_string(),
+            local_path: local_path.to_string(),
+            content_hash: Some(format!("hash-{}", drive_id)),
+            size: Some(1024),
+            modified_at: Some(uploaded_at - 1000),
+            name: "report".to_string(),
+            mime_type: Some("application/vnd.google-apps.document".to_string()),
+            file_type: "Google Docs".to_string(),
+            web_view_link: format!("https://docs.google.com/document/d/{}/edit", drive_id),
+            folder_id: Some("folder-1".to_string()),
+            first_uploaded_at: uploaded_at,
+            last_uploaded_at: uploaded_at,
+        }
+    }
+
+    #[test]
+    fn test_find_prefers_path_then_content() {
+        let db = memory_db();
+        db.remember(&record("file-a", "/docs/a.docx", 1000))
+            .unwrap();
+        db.remember(&record("file-b", "/docs/b.docx", 2000))
+            .unwrap();
+
+        let by_path = db.find("/docs/a.docx", "hash-file-b").unwrap().unwrap();
+        assert_eq!(by_path.drive_id, "file-a");
+
+        let moved = db
+            .find("/elsewhere/b.docx", "hash-file-b")
+            .unwrap()
+            .unwrap();
+        assert_eq!(moved.drive_id, "file-b");
+
+        assert!(db.find("/docs/c.docx", "hash-c").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_leaves_copies_of_live_files_alone() {
+        let dir = std::env::temp_dir().join("gopener_test_upload_db_duplicates");
+        std::fs::create_dir_all(&dir).unwrap();
+        let original = dir.join("budget.xlsx");
+        let duplicate = dir.join("budget copy.xlsx");
+        std::fs::write(&original, "same content").unwrap();
+        std::fs::write(&duplicate, "same content").unwrap();
+        let original = original.to_string_lossy().to_string();
+        let duplicate = duplicate.to_string_lossy().to_string();
+
+        let db = memory_db();
+        db.remember(&record("file-a", &original, 1000)).unwrap();
+        assert!(db.find(&duplicate, "hash-file-a").unwrap().is_none());
+
+        // Once the original is gone, its copy goes with the content
+        std::fs::remove_file(&original).unwrap();
+        let moved = db.find(&duplicate, "hash-file-a").unwrap().unwrap();
+        assert_eq!(moved.drive_id, "file-a");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_newer_databases_are_not_downgraded() {
+        let path = std::env::temp_dir().join("gopener_test_upload_db_newer.db");
+        let _ = fs::remove_file(&path);
+        let newer = MIGRATIONS.len() + 1;
+        Connection::open(&path)
+            .unwrap()
+            .pragma_update(None, "user_version", newer)
+            .unwrap();
+
+        assert!(UploadDb::with_connection(Connection::open(&path).unwrap(), None).is_err());
+
+        let version: usize = Connection::open(&path)
+            .unwrap()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, newer);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_history_reuses_its_connection() {
+        let history = UploadHistory {
+            db: Arc::new(Mutex::new(Some(memory_db()))),
+        };
+        history
+            .with(|db| db.remember(&record("file-a", "/docs/a.docx", 1000)))
+            .await
+            .unwrap();
+
+        // An in-memory database only lives as long as its connection
+        let stored = history
+            .clone()
+            .with(|db| db.by_drive_id("file-a"))
+            .await
+            .unwrap();
+        assert_eq!(stored.unwrap().local_path, "/docs/a.docx");
+    }
+
+    #[test]
+    fn test_remember_updates_row_and_keeps_first_upload() {
+        let db = memory_db();
+        db.remember(&record("file-a", "/docs/a.docx", 1000))
+            .unwrap();
+
+        // A revision knows neither the folder nor, for imports, the hash
+        let mut revision = record("file-a", "/moved/a.docx", 5000);
+        revision.folder_id = None;
+        revision.content_hash = None;
+        db.remember(&revision).unwrap();
+
+        let stored = db.by_drive_id("file-a").unwrap().unwrap();
+        assert_eq!(stored.local_path, "/moved/a.docx");
+        assert_eq!(stored.folder_id.as_deref(), Some("folder-1"));
+        assert_eq!(stored.content_hash.as_deref(), Some("hash-file-a"));
+        assert_eq!(stored.first_uploaded_at, 1000);
+        assert_eq!(stored.last_uploaded_at, 5000);
+
+        db.forget("file-a").unwrap();
+        assert!(db.by_drive_id("file-a").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_queries_by_path_and_folder_are_newest_first() {
+        let db = memory_db();
+        db.remember(&record("copy-1", "/docs/a.docx", 1000))
+            .unwrap();
+        db.remember(&record("copy-2", "/docs/a.docx", 3000))
+            .unwrap();
+        let mut elsewhere = record("other", "/docs/b.docx", 2000);
+        elsewhere.folder_id = Some("folder-2".to_string());
+        db.remember(&elsewhere).unwrap();
+
+        let ids = |records: Vec<UploadRecord>| -> Vec<String> {
+            records.into_iter().map(|r| r.drive_id).collect()
+        };
+        assert_eq!(
+            ids(db.by_path("/docs/a.docx").unwrap()),
+            ["copy-2", "copy-1"]
+        );
+        assert_eq!(ids(db.in_folder("folder-1").unwrap()), ["copy-2", "copy-1"]);
+        assert_eq!(ids(db.in_folder("folder-2").unwrap()), ["other"]);
+    }
+
+    #[test]
+    fn test_creating_the_database_imports_earlier_history() {
+        let dir = std::env::temp_dir().join("gopener_test_upload_db_import");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("settings.json"),
+            serde_json::json!({
+                "default_folder_id": null,
+                "default_folder_name": null,
+                "auto_open_after_upload": true,
+                "auto_close_after_upload": false,
+                "theme": "dark",
+                "recent_files": [{
+                    "id": "recent-1",
+                    "name": "notes",
+                    "path": "/docs/notes.docx",
+                    "google_url": "https://docs.google.com/document/d/recent-1/edit",
+                    "file_type": "Google Docs",
+                    "uploaded_at": 1700000000000u64
+                }]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let db_path = dir.join("uploads.db");
+        let db =
+            UploadDb::with_connection(Connection::open(&db_path).unwrap(), Some(&dir)).unwrap();
+
+        let recent = db.by_drive_id("recent-1").unwrap().unwrap();
+        assert_eq!(recent.local_path, "/docs/notes.docx");
+        assert_eq!(recent.content_hash, None);
+        drop(db);
+
+        // Opening an existing database does not import again
+        let db =
+            UploadDb::with_connection(Connection::open(&db_path).unwrap(), Some(&dir)).unwrap();
+        db.forget("recent-1").unwrap();
+        drop(db);
+        let db =
+            UploadDb::with_connection(Connection::open(&db_path).unwrap(), Some(&dir)).unwrap();
+        assert!(db.by_drive_id("recent-1").unwrap().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,374 @@
+//! Error type returned by every Tauri command.
+//!
+//! Commands reject with `{ code, message, retryable, reason }` so the frontend
+//! can branch on a stable `code` instead of matching message text. `reason` is
+//! the reason Google gave for a failed API call, when it gave one.
+
+use crate::commands::auth::AuthError;
+use crate::utils::keychain::KeychainError;
+use crate::utils::retry;
+use reqwest::StatusCode;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use thiserror::Error;
+
+/// Drive reasons meaning the account or project ran out of quota
+const QUOTA_REASONS: &[&str] = &[
+    "quotaExceeded",
+    "storageQuotaExceeded",
+    "dailyLimitExceeded",
+];
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum GopenerError {
+    #[error("Not signed in to Google")]
+    NotAuthenticated,
+    #[error("{0}")]
+    SignIn(String),
+    #[error("{0}")]
+    Cancelled(String),
+    #[error("{0}")]
+    TimedOut(String),
+    /// The sign-in callback answered a different request than the pending one
+    #[error("{0}")]
+    StateMismatch(String),
+    /// The authorization code was redeemed before
+    #[error("{0}")]
+    AuthorizationReused(String),
+    /// A code arrived while no sign-in was waiting for one
+    #[error("{0}")]
+    NoPendingSignIn(String),
+    #[error("{0}")]
+    Keychain(String),
+    #[error("Unsupported file type: {0}")]
+    UnsupportedFileType(String),
+    /// The content matches none of the supported formats, whatever the extension says
+    #[error("{0} does not contain a supported document, spreadsheet or presentation")]
+    UnrecognizedContent(String),
+    /// Drive can't convert a file that needs a password to open
+    #[error("{0} is protected with a password; remove the password to convert it")]
+    PasswordProtected(String),
+    /// Over one of Drive's conversion limits, such as 10 million cells in Sheets
+    #[error("{0}")]
+    ConversionLimit(String),
+    #[error("File does not exist: {0}")]
+    FileNotFound(String),
+    #[error("{0}")]
+    Io(String),
+    #[error("{0}")]
+    InvalidInput(String),
+    /// The file was uploaded before and the caller asked to be consulted
+    #[error("{0} was already uploaded to Google Drive")]
+    AlreadyUploaded(String),
+    #[error("Upload job not found: {0}")]
+    JobNotFound(String),
+    #[error("Upload job has already finished: {0}")]
+    JobFinished(String),
+    #[error("Upload session expired: {0}")]
+    SessionExpired(String),
+    #[error("{0}")]
+    Network(String),
+    #[error("{message}")]
+    Google {
+        status: u16,
+        reason: Option<String>,
+        message: String,
+    },
+    /// Only returned on platforms without file association support
+    #[allow(dead_code)]
+    #[error("{0}")]
+    UnsupportedPlatform(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl GopenerError {
+    /// Build an error from a failed Google response, prefixing its message with `context`
+    pub fn google(context: &str, status: StatusCode, body: &str) -> Self {
+        let (reason, detail) = parse_google_error(body);
+        let detail = detail.unwrap_or_else(|| {
+            if body.trim().is_empty() {
+                format!("HTTP {}", status.as_u16())
+            } else {
+                body.trim().to_string()
+            }
+        });
+
+        GopenerError::Google {
+            status: status.as_u16(),
+            reason,
+            message: format!("{}: {}", context, detail),
+        }
+    }
+
+    /// Stable identifier the frontend can branch on
+    pub fn code(&self) -> &'static str {
+        match self {
+            GopenerError::NotAuthenticated => "not_authenticated",
+            GopenerError::SignIn(_) => "sign_in_failed",
+            GopenerError::Cancelled(_) => "cancelled",
+            GopenerError::TimedOut(_) => "timed_out",
+            GopenerError::StateMismatch(_) => "state_mismatch",
+            GopenerError::AuthorizationReused(_) => "authorization_reused",
+            GopenerError::NoPendingSignIn(_) => "no_pending_sign_in",
+            GopenerError::Keychain(_) => "keychain_error",
+            GopenerError::UnsupportedFileType(_) => "unsupported_file_type",
+            GopenerError::UnrecognizedContent(_) => "unrecognized_content",
+            GopenerError::PasswordProtected(_) => "password_protected",
+            GopenerError::ConversionLimit(_) => "conversion_limit_exceeded",
+            GopenerError::FileNotFound(_) => "file_not_found",
+            GopenerError::Io(_) => "io_error",
+            GopenerError::InvalidInput(_) => "invalid_input",
+            GopenerError::AlreadyUploaded(_) => "already_uploaded",
+            GopenerError::JobNotFound(_) => "job_not_found",
+            GopenerError::JobFinished(_) => "job_finished",
+            GopenerError::SessionExpired(_) => "upload_session_expired",
+            GopenerError::Network(_) => "network_error",
+            GopenerError::Google { status, reason, .. } => google_code(*status, reason.as_deref()),
+            GopenerError::UnsupportedPlatform(_) => "unsupported_platform",
+            GopenerError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Whether running the same operation again may succeed
+    pub fn retryable(&self) -> bool {
+        match self {
+            GopenerError::TimedOut(_)
+            | GopenerError::SessionExpired(_)
+            | GopenerError::Network(_) => true,
+            GopenerError::Google { status, reason, .. } => {
+                matches!(
+                    google_code(*status, reason.as_deref()),
+                    "rate_limited" | "google_unavailable"
+                )
+            }
+            _ => false,
+        }
+    }
+
+    /// Reason Google attached to the failure, if any
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            GopenerError::Google { reason, .. } => reason.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for GopenerError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("GopenerError", 4)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("retryable", &self.retryable())?;
+        state.serialize_field("reason", &self.reason())?;
+        state.end()
+    }
+}
+
+impl From<KeychainError> for GopenerError {
+    fn from(e: KeychainError) -> Self {
+        GopenerError::Keychain(e.to_string())
+    }
+}
+
+impl From<AuthError> for GopenerError {
+    fn from(e: AuthError) -> Self {
+        match e {
+            AuthError::Cancelled => GopenerError::Cancelled(e.to_string()),
+            AuthError::TimedOut => GopenerError::TimedOut(e.to_string()),
+            AuthError::StateMismatch => GopenerError::StateMismatch(e.to_string()),
+            AuthError::AuthorizationReused => GopenerError::AuthorizationReused(e.to_string()),
+            AuthError::NoPendingAuthorization => GopenerError::NoPendingSignIn(e.to_string()),
+            AuthError::Keychain(e) => e.into(),
+            AuthError::TokenExchange(e) => e,
+            e => GopenerError::SignIn(e.to_string()),
+        }
+    }
+}
+
+fn google_code(status: u16, reason: Option<&str>) -> &'static str {
+    let reason = reason.unwrap_or_default();
+    if status == 429 || retry::RATE_LIMIT_REASONS.contains(&reason) {
+        return "rate_limited";
+    }
+    if QUOTA_REASONS.contains(&reason) {
+        return "quota_exceeded";
+    }
+    match status {
+        400 if reason == "invalid_grant" => "not_authenticated",
+        401 => "not_authenticated",
+        403 => "permission_denied",
+        404 => "not_found",
+        408 | 500..=599 => "google_unavailable",
+        _ => "google_api_error",
+    }
+}
+
+/// Pull the reason and message out of a Google error body.
+///
+/// Drive answers with `{"error": {"message", "errors": [{"reason"}]}}`, the
+/// OAuth token endpoint with `{"error": "...", "error_description": "..."}`.
+fn parse_google_error(body: &str) -> (Option<String>, Option<String>) {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        return (None, None);
+    };
+    let error = &value["error"];
+
+    if let Some(reason) = error.as_str() {
+        let message = value["error_description"].as_str().unwrap_or(reason);
+        return (Some(reason.to_string()), Some(message.to_string()));
+    }
+
+    let reason = error["errors"]
+        .as_array()
+        .and_then(|errors| errors.iter().find_map(|e| e["reason"].as_str()))
+        .or_else(|| error["status"].as_str())
+        .map(str::to_string);
+    let message = error["message"].as_str().map(str::to_string);
+    (reason, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drive_error(status: u16, reason: &str, message: &str) -> String {
+        serde_json::json!({
+            "error": {
+                "code": status,
+                "message": message,
+                "errors": [{"domain": "usageLimits", "reason": reason, "message": message}]
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_serializes_code_message_retryable_and_reason() {
+        let error = GopenerError::google(
+            "Upload failed",
+            StatusCode::FORBIDDEN,
+            &drive_error(403, "userRateLimitExceeded", "User rate limit exceeded."),
+        );
+
+        let json = serde_json::to_value(&error).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "code": "rate_limited",
+                "message": "Upload failed: User rate limit exceeded.",
+                "retryable": true,
+                "reason": "userRateLimitExceeded",
+            })
+        );
+    }
+
+    #[test]
+    fn test_google_errors_are_classified_by_reason_then_status() {
+        let quota = GopenerError::google(
+            "Upload failed",
+            StatusCode::FORBIDDEN,
+            &drive_error(
+                403,
+                "storageQuotaExceeded",
+                "The user's Drive storage quota has been exceeded.",
+            ),
+        );
+        assert_eq!(quota.code(), "quota_exceeded");
+        assert!(!quota.retryable());
+
+        let denied = GopenerError::google(
+            "API error",
+            StatusCode::FORBIDDEN,
+            &drive_error(403, "insufficientFilePermissions", "No access"),
+        );
+        assert_eq!(denied.code(), "permission_denied");
+        assert_eq!(denied.reason(), Some("insufficientFilePermissions"));
+
+        let missing = GopenerError::google("API error", StatusCode::NOT_FOUND, "File not found");
+        assert_eq!(missing.code(), "not_found");
+        assert_eq!(missing.reason(), None);
+        assert_eq!(missing.to_string(), "API error: File not found");
+
+        let unavailable = GopenerError::google("API error", StatusCode::BAD_GATEWAY, "");
+        assert_eq!(unavailable.code(), "google_unavailable");
+        assert!(unavailable.retryable());
+        assert_eq!(unavailable.to_string(), "API error: HTTP 502");
+    }
+
+    #[test]
+    fn test_oauth_errors_use_the_error_field_as_reason() {
+        let body = r#"{"error": "invalid_grant", "error_description": "Token has been expired or revoked."}"#;
+
+        let error = GopenerError::google("Token refresh failed", StatusCode::BAD_REQUEST, body);
+
+        assert_eq!(error.code(), "not_authenticated");
+        assert_eq!(error.reason(), Some("invalid_grant"));
+        assert_eq!(
+            error.to_string(),
+            "Token refresh failed: Token has been expired or revoked."
+        );
+    }
+
+    #[test]
+    fn test_auth_errors_keep_their_meaning() {
+        assert_eq!(GopenerError::from(AuthError::Cancelled).code(), "cancelled");
+        assert!(GopenerError::from(AuthError::TimedOut).retryable());
+
+        let denied = GopenerError::from(AuthError::Denied("access_denied".to_string()));
+        assert_eq!(denied.code(), "sign_in_failed");
+
+        let exchange = GopenerError::from(AuthError::TokenExchange(GopenerError::Network(
+            "connection reset".to_string(),
+        )));
+        assert_eq!(
+            exchange,
+            GopenerError::Network("connection reset".to_string())
+        );
+    }
+
+    #[test]
+    fn test_state_mismatch_has_its_own_code() {
+        let error = GopenerError::from(AuthError::StateMismatch);
+        assert_eq!(error.code(), "state_mismatch");
+        assert_eq!(error.to_string(), AuthError::StateMismatch.to_string());
+        assert!(!error.retryable());
+    }
+
+    #[test]
+    fn test_reused_authorization_has_its_own_code() {
+        let error = GopenerError::from(AuthError::AuthorizationReused);
+        assert_eq!(error.code(), "authorization_reused");
+        assert_eq!(
+            error.to_string(),
+            AuthError::AuthorizationReused.to_string()
+        );
+    }
+
+    #[test]
+    fn test_code_without_pending_sign_in_has_its_own_code() {
+        let error = GopenerError::from(AuthError::NoPendingAuthorization);
+        assert_eq!(error.code(), "no_pending_sign_in");
+        assert_eq!(
+            error.to_string(),
+            AuthError::NoPendingAuthorization.to_string()
+        );
+    }
+
+    #[test]
+    fn test_local_errors_have_no_reason() {
+        let error = GopenerError::UnsupportedFileType("pdf".to_string());
+        let json = serde_json::to_value(&error).unwrap();
+
+        assert_eq!(json["code"], "unsupported_file_type");
+        assert_eq!(json["message"], "Unsupported file type: pdf");
+        assert_eq!(json["retryable"], false);
+        assert!(json["reason"].is_null());
+
+        let locked = GopenerError::PasswordProtected("Budget.xlsx".to_string());
+        assert_eq!(locked.code(), "password_protected");
+        assert!(!locked.retryable());
+    }
+}
@@ -1,69 +1,318 @@
 use crate::commands::auth::get_valid_token;
+use crate::commands::storage::get_settings;
 use crate::config;
+use crate::error::GopenerError;
+use crate::utils::retry::RetryPolicy;
 use reqwest::Client;
 use serde::de::DeserializeOwned;
 
+/// One page of results from a Drive list endpoint
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Token for the following page; `None` on the last page
+    pub next_page_token: Option<String>,
+}
+
 pub struct GoogleClient {
     client: Client,
     access_token: String,
+    base_url: String,
+    retry: RetryPolicy,
 }
 
 impl GoogleClient {
-    pub async fn new() -> Result<Self, String> {
+    pub async fn new() -> Result<Self, GopenerError> {
         let access_token = get_valid_token().await?;
-        Ok(Self {
+        Ok(Self::with_config(
+            config::GOOGLE_DRIVE_API_BASE,
+            access_token,
+            get_settings().await?.retry_policy(),
+        ))
+    }
+
+    pub(crate) fn with_config(base_url: &str, access_token: String, retry: RetryPolicy) -> Self {
+        Self {
             client: Client::new(),
             access_token,
-        })
+            base_url: base_url.to_string(),
+            retry,
+        }
     }
 
-    pub async fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T, String> {
-        let url = format!("{}{}", config::GOOGLE_DRIVE_API_BASE, endpoint);
+    pub async fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T, GopenerError> {
+        let url = format!("{}{}", self.base_url, endpoint);
 
         let response = self
-            .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.access_token))
-            .send()
+            .retry
+            .send(|| {
+                self.client
+                    .get(&url)
+                    .header("Authorization", format!("Bearer {}", self.access_token))
+            })
             .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .map_err(|e| GopenerError::Network(format!("Request failed: {}", e)))?;
 
-        if !response.status().is_success() {
+        let status = response.status();
+        if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("API error: {}", error_text));
+            return Err(GopenerError::google("API error", status, &error_text));
         }
 
         response
             .json()
             .await
-            .map_err(|e| format!("Failed to parse response: {}", e))
+            .map_err(|e| GopenerError::Internal(format!("Failed to parse response: {}", e)))
+    }
+
+    /// Fetch one page of a list endpoint.
+    ///
+    /// `items_field` names the array holding the results (`files`, `drives`, ...).
+    /// The endpoint's `fields` parameter must include `nextPageToken` for further
+    /// pages to be reported.
+    pub async fn get_page<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        items_field: &str,
+        page_token: Option<&str>,
+    ) -> Result<Page<T>, GopenerError> {
+        let endpoint = match page_token {
+            Some(token) => {
+                let separator = if endpoint.contains('?') { '&' } else { '?' };
+                format!(
+                    "{}{}pageToken={}",
+                    endpoint,
+                    separator,
+                    urlencoding::encode(token)
+                )
+            }
+            None => endpoint.to_string(),
+        };
+
+        let mut response: serde_json::Value = self.get(&endpoint).await?;
+        let items = match response.get_mut(items_field) {
+            Some(items) => serde_json::from_value(items.take())
+                .map_err(|e| GopenerError::Internal(format!("Failed to parse response: {}", e)))?,
+            None => Vec::new(),
+        };
+        let next_page_token = response["nextPageToken"]
+            .as_str()
+            .filter(|token| !token.is_empty())
+            .map(str::to_string);
+
+        Ok(Page {
+            items,
+            next_page_token,
+        })
+    }
+
+    /// Follow `nextPageToken` until the last page and return every item
+    pub async fn get_all<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        items_field: &str,
+    ) -> Result<Vec<T>, GopenerError> {
+        let mut items = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let page = self
+                .get_page(endpoint, items_field, page_token.as_deref())
+                .await?;
+            items.extend(page.items);
+
+            match page.next_page_token {
+                // A token that doesn't move forward would loop forever
+                Some(token) if page_token.as_deref() != Some(token.as_str()) => {
+                    page_token = Some(token)
+                }
+                _ => return Ok(items),
+            }
+        }
     }
 
     pub async fn post<T: DeserializeOwned>(
         &self,
         endpoint: &str,
         body: &serde_json::Value,
-    ) -> Result<T, String> {
-        let url = format!("{}{}", config::GOOGLE_DRIVE_API_BASE, endpoint);
+    ) -> Result<T, GopenerError> {
+        let url = format!("{}{}", self.base_url, endpoint);
 
+        // A POST creates something, so it is only repeated when Google can't have acted on it
         let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.access_token))
-            .header("Content-Type", "application/json")
-            .json(body)
-            .send()
+            .retry
+            .non_idempotent()
+            .send(|| {
+                self.client
+                    .post(&url)
+                    .header("Authorization", format!("Bearer {}", self.access_token))
+                    .header("Content-Type", "application/json")
+                    .json(body)
+            })
             .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .map_err(|e| GopenerError::Network(format!("Request failed: {}", e)))?;
 
-        if !response.status().is_success() {
+        let status = response.status();
+        if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("API error: {}", error_text));
+            return Err(GopenerError::google("API error", status, &error_text));
         }
 
         response
             .json()
             .await
-            .map_err(|e| format!("Failed to parse response: {}", e))
+            .map_err(|e| GopenerError::Internal(format!("Failed to parse response: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{ScriptedResponse, TestServer};
+    use std::time::Duration;
+
+    fn client(server: &TestServer) -> GoogleClient {
+        GoogleClient::with_config(
+            &server.url,
+            "token".to_string(),
+            RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(10),
+                jitter: false,
+                idempotent: true,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_get_retries_transient_errors() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::new(502, "bad gateway"),
+            ScriptedResponse::json(200, serde_json::json!({"id": "f1"})),
+        ])
+        .await;
+
+        let value: serde_json::Value = client(&server).get("/files/f1").await.unwrap();
+
+        assert_eq!(value["id"], "f1");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests
+            .iter()
+            .all(|r| r.path == "/files/f1" && r.header("authorization") == Some("Bearer token")));
+    }
+
+    #[tokio::test]
+    async fn test_post_resends_body_on_retry() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::new(429, "rate limited").header("Retry-After", "0"),
+            ScriptedResponse::json(200, serde_json::json!({"id": "folder-1"})),
+        ])
+        .await;
+
+        let value: serde_json::Value = client(&server)
+            .post("/files", &serde_json::json!({"name": "Reports"}))
+            .await
+            .unwrap();
+
+        assert_eq!(value["id"], "folder-1");
+        for request in server.requests() {
+            assert!(request.body_text().contains("Reports"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_post_is_not_repeated_after_server_error() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::new(500, "backend error"),
+            ScriptedResponse::json(200, serde_json::json!({"id": "folder-2"})),
+        ])
+        .await;
+
+        let result: Result<serde_json::Value, _> = client(&server)
+            .post("/files", &serde_json::json!({"name": "Reports"}))
+            .await;
+
+        assert_eq!(result.unwrap_err().code(), "google_unavailable");
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_page_passes_token_and_returns_next() {
+        let server = TestServer::start(vec![ScriptedResponse::json(
+            200,
+            serde_json::json!({"files": [{"id": "a"}, {"id": "b"}], "nextPageToken": "p3"}),
+        )])
+        .await;
+
+        let page: Page<serde_json::Value> = client(&server)
+            .get_page("/files?pageSize=2", "files", Some("p2"))
+            .await
+            .unwrap();
+
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.next_page_token.as_deref(), Some("p3"));
+        assert_eq!(server.requests()[0].path, "/files?pageSize=2&pageToken=p2");
+    }
+
+    #[tokio::test]
+    async fn test_get_page_without_results() {
+        let server =
+            TestServer::start(vec![ScriptedResponse::json(200, serde_json::json!({}))]).await;
+
+        let page: Page<serde_json::Value> = client(&server)
+            .get_page("/drives", "drives", None)
+            .await
+            .unwrap();
+
+        assert!(page.items.is_empty());
+        assert!(page.next_page_token.is_none());
+        assert_eq!(server.requests()[0].path, "/drives");
+    }
+
+    #[tokio::test]
+    async fn test_get_all_follows_every_page() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::json(
+                200,
+                serde_json::json!({"files": [{"id": "a"}], "nextPageToken": "t/2"}),
+            ),
+            ScriptedResponse::json(
+                200,
+                serde_json::json!({"files": [{"id": "b"}], "nextPageToken": "t3"}),
+            ),
+            ScriptedResponse::json(200, serde_json::json!({"files": [{"id": "c"}]})),
+        ])
+        .await;
+
+        let files: Vec<serde_json::Value> = client(&server)
+            .get_all("/files?q=x", "files")
+            .await
+            .unwrap();
+
+        let ids: Vec<_> = files.iter().map(|f| f["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+        let paths: Vec<_> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(
+            paths,
+            [
+                "/files?q=x",
+                "/files?q=x&pageToken=t%2F2",
+                "/files?q=x&pageToken=t3"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_reports_non_retryable_errors() {
+        let server = TestServer::start(vec![ScriptedResponse::new(404, "File not found")]).await;
+
+        let result: Result<serde_json::Value, GopenerError> = client(&server).get("/files/x").await;
+
+        let err = result.unwrap_err();
+        assert_eq!(err.to_string(), "API error: File not found");
+        assert_eq!(err.code(), "not_found");
+        assert_eq!(server.requests().len(), 1);
     }
 }
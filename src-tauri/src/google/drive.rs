@@ -1,64 +1,404 @@
+use crate::error::GopenerError;
 use crate::google::client::GoogleClient;
+use query::Query;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
 
+pub mod folder_path;
+// The builder covers every term uploads may need, not only the ones used today
+#[allow(dead_code)]
+pub mod query;
+
+/// Query parameters that let a request see and act on shared drive items
+const ALL_DRIVES_PARAMS: &str = "supportsAllDrives=true&includeItemsFromAllDrives=true";
+
+/// Fields requested for every folder
+const FOLDER_FIELDS: &str =
+    "id,name,mimeType,driveId,parents,owners(displayName),capabilities(canAddChildren)";
+
+/// Results returned by `search_folders`
+const SEARCH_PAGE_SIZE: u32 = 50;
+
+/// Ancestors followed when building a breadcrumb, in case of a parent cycle
+const MAX_BREADCRUMB_DEPTH: usize = 32;
+
+/// MIME type Drive uses for folders
+const FOLDER_MIME_TYPE: &str = "application/vnd.google-apps.folder";
+
+/// Folder as shown to the frontend; read from Drive's file resource
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(from = "FolderResource")]
 pub struct DriveFolder {
     pub id: String,
     pub name: String,
     #[serde(rename = "mimeType")]
     pub mime_type: String,
+    /// Shared drive holding the folder; `None` for My Drive
+    #[serde(rename = "driveId", skip_serializing_if = "Option::is_none")]
+    pub drive_id: Option<String>,
+    /// Display name of the owner; shared drive folders have none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Whether the user may create files and folders inside it
+    #[serde(rename = "canAddChildren")]
+    pub can_add_children: bool,
+    #[serde(skip_serializing)]
+    pub parents: Vec<String>,
+}
+
+/// Folder found by `search_folders`, with the names of its ancestors
+#[derive(Debug, Serialize, Clone)]
+pub struct FolderSearchResult {
+    #[serde(flatten)]
+    pub folder: DriveFolder,
+    /// Names from the top of the drive down to the folder itself
+    pub path: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FolderResource {
+    id: String,
+    name: String,
+    mime_type: String,
+    drive_id: Option<String>,
+    #[serde(default)]
+    parents: Vec<String>,
+    #[serde(default)]
+    owners: Vec<Owner>,
+    capabilities: Option<Capabilities>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Owner {
+    display_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Capabilities {
+    can_add_children: Option<bool>,
+}
+
+impl From<FolderResource> for DriveFolder {
+    fn from(resource: FolderResource) -> Self {
+        DriveFolder {
+            id: resource.id,
+            name: resource.name,
+            mime_type: resource.mime_type,
+            drive_id: resource.drive_id,
+            owner: resource
+                .owners
+                .into_iter()
+                .find_map(|owner| owner.display_name),
+            // Drive leaves capabilities out when they weren't requested; assume writable
+            can_add_children: resource
+                .capabilities
+                .and_then(|c| c.can_add_children)
+                .unwrap_or(true),
+            parents: resource.parents,
+        }
+    }
+}
+
+/// Starting points for browsing besides My Drive and shared drives
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderRoot {
+    /// Folders other people shared with the user
+    SharedWithMe,
+    Starred,
+    /// Folders the user opened in the last 30 days, most recent first
+    Recent,
+}
+
+/// A shared drive the user is a member of
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SharedDrive {
+    pub id: String,
+    pub name: String,
+}
+
+/// Folders returned per `list_folders` call
+const FOLDER_PAGE_SIZE: u32 = 100;
+
+/// How far back the Recent root looks; older folders are no longer recent
+const RECENT_WINDOW: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// One page of folders; pass `next_page_token` back to get the rest
+#[derive(Debug, Serialize, Clone)]
+pub struct FolderPage {
+    pub folders: Vec<DriveFolder>,
+    pub next_page_token: Option<String>,
+}
+
+/// List the shared drives the user can browse
+#[tauri::command]
+pub async fn list_shared_drives() -> Result<Vec<SharedDrive>, GopenerError> {
+    let client = GoogleClient::new().await?;
+
+    client
+        .get_all("/drives?pageSize=100&fields=nextPageToken,drives(id,name)", "drives")
+        .await
 }
 
-#[derive(Debug, Deserialize)]
-struct FolderListResponse {
-    files: Vec<DriveFolder>,
-    #[allow(dead_code)]
-    #[serde(rename = "nextPageToken")]
-    next_page_token: Option<String>,
+/// List folders in Google Drive, one page at a time.
+///
+/// With `drive_id` set the listing is scoped to that shared drive, and a
+/// missing `parent_id` means the top of the shared drive.
+#[tauri::command]
+pub async fn list_folders(
+    parent_id: Option<String>,
+    drive_id: Option<String>,
+    page_token: Option<String>,
+) -> Result<FolderPage, GopenerError> {
+    let client = GoogleClient::new().await?;
+
+    let page = client
+        .get_page(
+            &folder_list_endpoint(parent_id.as_deref(), drive_id.as_deref()),
+            "files",
+            page_token.as_deref(),
+        )
+        .await?;
+
+    Ok(FolderPage {
+        folders: page.items,
+        next_page_token: page.next_page_token,
+    })
 }
 
-/// List folders in Google Drive
+/// List the folders of a browsing root such as "Shared with me", one page at a time
 #[tauri::command]
-pub async fn list_folders(parent_id: Option<String>) -> Result<Vec<DriveFolder>, String> {
+pub async fn list_root_folders(
+    root: FolderRoot,
+    page_token: Option<String>,
+) -> Result<FolderPage, GopenerError> {
     let client = GoogleClient::new().await?;
 
-    // Build query to get folders only
-    let mut query = "mimeType='application/vnd.google-apps.folder' and trashed=false".to_string();
+    let page = client
+        .get_page(&root_endpoint(root), "files", page_token.as_deref())
+        .await?;
+
+    Ok(FolderPage {
+        folders: page.items,
+        next_page_token: page.next_page_token,
+    })
+}
 
-    if let Some(ref parent) = parent_id {
-        query = format!("{} and '{}' in parents", query, parent);
-    } else {
-        // Root level - get items in "My Drive"
-        query = format!("{} and 'root' in parents", query);
+/// Find folders whose name contains `query` across My Drive and shared drives
+#[tauri::command]
+pub async fn search_folders(query: String) -> Result<Vec<FolderSearchResult>, GopenerError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(GopenerError::InvalidInput(
+            "Enter part of a folder name to search for".to_string(),
+        ));
     }
 
+    let client = GoogleClient::new().await?;
+    find_folders(&client, query).await
+}
+
+async fn find_folders(
+    client: &GoogleClient,
+    query: &str,
+) -> Result<Vec<FolderSearchResult>, GopenerError> {
+    let page = client
+        .get_page::<DriveFolder>(&search_endpoint(query), "files", None)
+        .await?;
+
+    // Siblings share most of their ancestors, so look each one up only once
+    let mut ancestors = HashMap::new();
+    let mut results = Vec::with_capacity(page.items.len());
+    for folder in page.items {
+        let mut path = breadcrumb(client, &folder, &mut ancestors).await;
+        path.push(folder.name.clone());
+        results.push(FolderSearchResult { folder, path });
+    }
+    Ok(results)
+}
+
+/// Ancestor of a search result as needed for its breadcrumb
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Ancestor {
+    name: String,
+    #[serde(default)]
+    parents: Vec<String>,
+    drive_id: Option<String>,
+}
+
+/// Names of the ancestors of `folder`, outermost first.
+///
+/// Walking stops early at an ancestor the user cannot see, which is normal for
+/// folders shared from someone else's drive.
+async fn breadcrumb(
+    client: &GoogleClient,
+    folder: &DriveFolder,
+    ancestors: &mut HashMap<String, Option<Ancestor>>,
+) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut next = folder.parents.first().cloned();
+
+    while let Some(id) = next.take() {
+        if names.len() >= MAX_BREADCRUMB_DEPTH {
+            break;
+        }
+        if !ancestors.contains_key(&id) {
+            let ancestor = fetch_ancestor(client, &id).await;
+            ancestors.insert(id.clone(), ancestor);
+        }
+        let Some(ancestor) = &ancestors[&id] else {
+            break;
+        };
+        names.push(ancestor.name.clone());
+        next = ancestor.parents.first().cloned();
+    }
+
+    names.reverse();
+    names
+}
+
+async fn fetch_ancestor(client: &GoogleClient, id: &str) -> Option<Ancestor> {
     let endpoint = format!(
-        "/files?q={}&fields=files(id,name,mimeType)&orderBy=name&pageSize=100",
-        urlencoding::encode(&query)
+        "/files/{}?fields=name,parents,driveId&supportsAllDrives=true",
+        urlencoding::encode(id)
     );
+    let mut ancestor: Ancestor = client.get(&endpoint).await.ok()?;
+
+    // The top folder of a shared drive is named after the drive only in the drives API
+    if ancestor.parents.is_empty() && ancestor.drive_id.as_deref() == Some(id) {
+        let endpoint = format!("/drives/{}?fields=id,name", urlencoding::encode(id));
+        if let Ok(drive) = client.get::<SharedDrive>(&endpoint).await {
+            ancestor.name = drive.name;
+        }
+    }
+    Some(ancestor)
+}
 
-    let response: FolderListResponse = client.get(&endpoint).await?;
+/// Files endpoint searching folder names across every drive the user can see
+fn search_endpoint(name: &str) -> String {
+    let query = folders().and(Query::name_contains(name));
+    format!(
+        "/files?q={}&fields=files({})&pageSize={}&corpora=allDrives&{}",
+        urlencoding::encode(&query.to_string()),
+        FOLDER_FIELDS,
+        SEARCH_PAGE_SIZE,
+        ALL_DRIVES_PARAMS
+    )
+}
 
-    Ok(response.files)
+/// Folders that are not in the trash
+fn folders() -> Query {
+    Query::mime_type(FOLDER_MIME_TYPE).and(Query::trashed(false))
+}
+
+/// List every folder under a parent, following all pages
+#[tauri::command]
+pub async fn list_all_folders(
+    parent_id: Option<String>,
+    drive_id: Option<String>,
+) -> Result<Vec<DriveFolder>, GopenerError> {
+    let client = GoogleClient::new().await?;
+
+    client
+        .get_all(
+            &folder_list_endpoint(parent_id.as_deref(), drive_id.as_deref()),
+            "files",
+        )
+        .await
+}
+
+/// Files endpoint listing the folders directly under a parent.
+///
+/// Without a parent this is the top of the shared drive `drive_id`, or of My
+/// Drive when no drive is given either.
+fn folder_list_endpoint(parent_id: Option<&str>, drive_id: Option<&str>) -> String {
+    // The root folder of a shared drive has the drive's ID
+    let parent = parent_id.or(drive_id).unwrap_or("root");
+    let query = folders().and(Query::in_parents(parent));
+
+    // Shared drive items are only searched when the corpus includes them
+    let corpora = match drive_id {
+        Some(drive) => format!("corpora=drive&driveId={}", urlencoding::encode(drive)),
+        None if parent_id.is_some() => "corpora=allDrives".to_string(),
+        None => "corpora=user".to_string(),
+    };
+
+    folder_query_endpoint(&query, "name", &corpora)
+}
+
+/// Files endpoint listing the folders of a browsing root
+fn root_endpoint(root: FolderRoot) -> String {
+    match root {
+        // Shared items are never in a shared drive, so the user corpus is enough
+        FolderRoot::SharedWithMe => folder_query_endpoint(
+            &folders().and(Query::shared_with_me()),
+            "name",
+            "corpora=user",
+        ),
+        FolderRoot::Starred => folder_query_endpoint(
+            &folders().and(Query::starred()),
+            "name",
+            "corpora=allDrives",
+        ),
+        // Folders never opened have no viewedByMeTime, so the cutoff also leaves them out
+        FolderRoot::Recent => {
+            let cutoff = SystemTime::now()
+                .checked_sub(RECENT_WINDOW)
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            folder_query_endpoint(
+                &folders().and(Query::viewed_by_me_after(cutoff)),
+                "viewedByMeTime desc",
+                "corpora=allDrives",
+            )
+        }
+    }
+}
+
+/// Files endpoint running a folder query over the given corpora
+fn folder_query_endpoint(query: &Query, order_by: &str, corpora: &str) -> String {
+    format!(
+        "/files?q={}&fields=nextPageToken,files({})&orderBy={}&pageSize={}&{}&{}",
+        urlencoding::encode(&query.to_string()),
+        FOLDER_FIELDS,
+        urlencoding::encode(order_by),
+        FOLDER_PAGE_SIZE,
+        corpora,
+        ALL_DRIVES_PARAMS
+    )
 }
 
 /// Create a new folder in Google Drive
 #[tauri::command]
-pub async fn create_folder(name: String, parent_id: Option<String>) -> Result<DriveFolder, String> {
+pub async fn create_folder(
+    name: String,
+    parent_id: Option<String>,
+) -> Result<DriveFolder, GopenerError> {
     let client = GoogleClient::new().await?;
+    new_folder(&client, &name, parent_id.as_deref()).await
+}
 
+/// Create a folder named `name` under `parent_id`, or at the top of My Drive
+async fn new_folder(
+    client: &GoogleClient,
+    name: &str,
+    parent_id: Option<&str>,
+) -> Result<DriveFolder, GopenerError> {
     let mut metadata = serde_json::json!({
         "name": name,
-        "mimeType": "application/vnd.google-apps.folder"
+        "mimeType": FOLDER_MIME_TYPE
     });
 
     if let Some(parent) = parent_id {
         metadata["parents"] = serde_json::json!([parent]);
     }
 
-    let folder: DriveFolder = client
-        .post("/files?fields=id,name,mimeType", &metadata)
-        .await?;
+    let endpoint = format!("/files?fields={}&supportsAllDrives=true", FOLDER_FIELDS);
+    let folder: DriveFolder = client.post(&endpoint, &metadata).await?;
 
     Ok(folder)
 }
@@ -83,6 +423,23 @@ pub fn get_file_url(file_id: &str, file_type: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::retry::RetryPolicy;
+    use crate::utils::test_server::{ScriptedResponse, TestServer};
+    use std::time::Duration;
+
+    fn client(server: &TestServer) -> GoogleClient {
+        GoogleClient::with_config(
+            &server.url,
+            "token".to_string(),
+            RetryPolicy {
+                max_attempts: 1,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(1),
+                jitter: false,
+                idempotent: true,
+            },
+        )
+    }
 
     #[test]
     fn test_get_file_url_document() {
@@ -117,18 +474,176 @@ mod tests {
         assert_eq!(url, "https://drive.google.com/file/d/file000/view");
     }
 
+    #[test]
+    fn test_folder_list_endpoint_requests_page_token() {
+        let endpoint = folder_list_endpoint(Some("folder-1"), None);
+
+        assert!(endpoint.contains(&format!("fields=nextPageToken,files({})", FOLDER_FIELDS)));
+        assert!(endpoint.contains("pageSize=100"));
+        assert!(endpoint.contains(&*urlencoding::encode("'folder-1' in parents")));
+    }
+
+    #[test]
+    fn test_folder_list_endpoint_my_drive_root() {
+        let endpoint = folder_list_endpoint(None, None);
+
+        assert!(endpoint.contains(&*urlencoding::encode("'root' in parents")));
+        assert!(endpoint.contains("corpora=user"));
+        assert!(endpoint.contains(ALL_DRIVES_PARAMS));
+    }
+
+    #[test]
+    fn test_folder_list_endpoint_shared_drive() {
+        let root = folder_list_endpoint(None, Some("drive-1"));
+        assert!(root.contains(&*urlencoding::encode("'drive-1' in parents")));
+        assert!(root.contains("corpora=drive&driveId=drive-1"));
+        assert!(root.contains("supportsAllDrives=true&includeItemsFromAllDrives=true"));
+
+        let nested = folder_list_endpoint(Some("folder-9"), Some("drive-1"));
+        assert!(nested.contains(&*urlencoding::encode("'folder-9' in parents")));
+        assert!(nested.contains("corpora=drive&driveId=drive-1"));
+
+        // A folder opened without its drive can still live in a shared drive
+        let unscoped = folder_list_endpoint(Some("folder-9"), None);
+        assert!(unscoped.contains("corpora=allDrives"));
+    }
+
+    #[test]
+    fn test_shared_drive_folder_deserialization() {
+        let json = r#"{"id":"f1","name":"Team","mimeType":"application/vnd.google-apps.folder","driveId":"d1"}"#;
+        let folder: DriveFolder = serde_json::from_str(json).unwrap();
+
+        assert_eq!(folder.drive_id.as_deref(), Some("d1"));
+        assert!(serde_json::to_string(&folder).unwrap().contains("\"driveId\":\"d1\""));
+    }
+
     #[test]
     fn test_drive_folder_serialization() {
         let folder = DriveFolder {
             id: "folder-1".to_string(),
             name: "My Folder".to_string(),
             mime_type: "application/vnd.google-apps.folder".to_string(),
+            drive_id: None,
+            owner: None,
+            can_add_children: true,
+            parents: vec!["root".to_string()],
         };
 
         let json = serde_json::to_string(&folder).unwrap();
         assert!(json.contains("\"id\":\"folder-1\""));
         assert!(json.contains("\"name\":\"My Folder\""));
         assert!(json.contains("\"mimeType\""));
+        assert!(json.contains("\"canAddChildren\":true"));
+        assert!(!json.contains("owner"));
+        assert!(!json.contains("parents"));
+    }
+
+    #[test]
+    fn test_drive_folder_reads_owner_and_capabilities() {
+        let json = r#"{
+            "id": "f2",
+            "name": "Shared",
+            "mimeType": "application/vnd.google-apps.folder",
+            "owners": [{"displayName": "Ada Lovelace"}],
+            "capabilities": {"canAddChildren": false}
+        }"#;
+        let folder: DriveFolder = serde_json::from_str(json).unwrap();
+
+        assert_eq!(folder.owner.as_deref(), Some("Ada Lovelace"));
+        assert!(!folder.can_add_children);
+    }
+
+    #[test]
+    fn test_root_endpoints() {
+        let shared = root_endpoint(FolderRoot::SharedWithMe);
+        assert!(shared.contains(&*urlencoding::encode(" and sharedWithMe")));
+        assert!(shared.contains("corpora=user"));
+
+        let starred = root_endpoint(FolderRoot::Starred);
+        assert!(starred.contains(&*urlencoding::encode("and starred = true")));
+        assert!(starred.contains("corpora=allDrives"));
+
+        let recent = root_endpoint(FolderRoot::Recent);
+        assert!(recent.contains("orderBy=viewedByMeTime%20desc"));
+        assert!(recent.contains(&*urlencoding::encode(" and viewedByMeTime > '")));
+        assert!(!recent.contains(&*urlencoding::encode(" in parents")));
+    }
+
+    #[test]
+    fn test_folder_list_endpoint_escapes_parent_id() {
+        let endpoint = folder_list_endpoint(Some("x' in parents or '"), None);
+
+        assert!(endpoint.contains(&*urlencoding::encode(r"'x\' in parents or \'' in parents")));
+    }
+
+    #[test]
+    fn test_search_endpoint_covers_all_drives() {
+        let endpoint = search_endpoint("Q1 'draft'");
+        let query = urlencoding::encode(
+            "mimeType = 'application/vnd.google-apps.folder' and trashed = false \
+             and name contains 'Q1 \\'draft\\''",
+        );
+        assert!(endpoint.contains(&*query));
+        assert!(endpoint.contains("corpora=allDrives"));
+        assert!(endpoint.contains(ALL_DRIVES_PARAMS));
+        assert!(endpoint.contains("parents"));
+    }
+
+    #[tokio::test]
+    async fn test_find_folders_builds_breadcrumbs() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::json(
+                200,
+                serde_json::json!({"files": [
+                    {"id": "f1", "name": "Invoices", "mimeType": FOLDER_MIME_TYPE,
+                     "driveId": "d1", "parents": ["p1"]},
+                    {"id": "f2", "name": "Receipts", "mimeType": FOLDER_MIME_TYPE,
+                     "driveId": "d1", "parents": ["p1"]},
+                ]}),
+            ),
+            ScriptedResponse::json(
+                200,
+                serde_json::json!({"name": "Finance", "parents": ["d1"], "driveId": "d1"}),
+            ),
+            ScriptedResponse::json(200, serde_json::json!({"name": "Drive", "driveId": "d1"})),
+            ScriptedResponse::json(200, serde_json::json!({"id": "d1", "name": "Team Drive"})),
+        ])
+        .await;
+
+        let results = find_folders(&client(&server), "o").await.unwrap();
+
+        assert_eq!(results[0].path, ["Team Drive", "Finance", "Invoices"]);
+        assert_eq!(results[1].path, ["Team Drive", "Finance", "Receipts"]);
+        // The shared ancestors are only fetched for the first result
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_breadcrumb_stops_at_inaccessible_parent() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::json(
+                200,
+                serde_json::json!({"files": [
+                    {"id": "f1", "name": "Shared", "mimeType": FOLDER_MIME_TYPE,
+                     "parents": ["hidden"]},
+                ]}),
+            ),
+            ScriptedResponse::json(404, serde_json::json!({"error": {"code": 404}})),
+        ])
+        .await;
+
+        let results = find_folders(&client(&server), "Shared").await.unwrap();
+
+        assert_eq!(results[0].path, ["Shared"]);
+        let json = serde_json::to_value(&results[0]).unwrap();
+        assert_eq!(json["id"], "f1");
+        assert_eq!(json["path"], serde_json::json!(["Shared"]));
+    }
+
+    #[test]
+    fn test_folder_root_deserialization() {
+        let root: FolderRoot = serde_json::from_str("\"shared_with_me\"").unwrap();
+        assert_eq!(root, FolderRoot::SharedWithMe);
     }
 
     #[test]
@@ -139,5 +654,7 @@ mod tests {
         assert_eq!(folder.id, "f1");
         assert_eq!(folder.name, "Test");
         assert_eq!(folder.mime_type, "application/vnd.google-apps.folder");
+        assert!(folder.owner.is_none());
+        assert!(folder.can_add_children);
     }
 }
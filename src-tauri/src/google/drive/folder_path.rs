@@ -0,0 +1,237 @@
+//! Resolve slash-separated Drive paths such as `Clients/Acme/2026/Q4` to folder IDs.
+//!
+//! Each segment is looked up among the folders of the previous one and created
+//! when missing. Drive allows several folders with the same name under one
+//! parent; the oldest of them is used, so a path always leads to the same folder
+//! no matter which duplicate was created last.
+
+use super::query::Query;
+use super::{folder_query_endpoint, folders, new_folder, DriveFolder};
+use crate::error::GopenerError;
+use crate::google::client::GoogleClient;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// ID Drive accepts for the top of My Drive
+const MY_DRIVE_ROOT: &str = "root";
+
+/// Folder IDs already resolved this session, keyed by parent ID and folder name.
+///
+/// Resolving holds the lock for the whole path, so concurrent uploads into a
+/// new path create each folder once instead of racing to create duplicates.
+#[derive(Debug, Clone, Default)]
+pub struct FolderPathCache {
+    inner: Arc<Mutex<HashMap<(String, String), String>>>,
+}
+
+impl FolderPathCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ID of the folder at `path` below `base_id`, or below My Drive when no base is given.
+    ///
+    /// Missing folders along the way are created. An empty path is the base itself.
+    pub async fn resolve(
+        &self,
+        client: &GoogleClient,
+        base_id: Option<&str>,
+        path: &str,
+    ) -> Result<String, GopenerError> {
+        let mut cache = self.inner.lock().await;
+        let mut parent = base_id.unwrap_or(MY_DRIVE_ROOT).to_string();
+
+        for name in segments(path) {
+            let key = (parent.clone(), name.to_string());
+            let id = match cache.get(&key) {
+                Some(id) => id.clone(),
+                None => {
+                    let id = match find_child(client, &parent, name).await? {
+                        Some(folder) => folder.id,
+                        None => new_folder(client, name, Some(&parent)).await?.id,
+                    };
+                    cache.insert(key, id.clone());
+                    id
+                }
+            };
+            parent = id;
+        }
+
+        Ok(parent)
+    }
+
+    /// Drop the cached IDs along `path`, so the next resolve looks each folder up again.
+    ///
+    /// Used when Drive no longer knows a cached folder, e.g. after it was deleted.
+    pub async fn evict(&self, base_id: Option<&str>, path: &str) {
+        let mut cache = self.inner.lock().await;
+        let mut parent = base_id.unwrap_or(MY_DRIVE_ROOT).to_string();
+
+        for name in segments(path) {
+            match cache.remove(&(parent, name.to_string())) {
+                Some(id) => parent = id,
+                None => break,
+            }
+        }
+    }
+}
+
+/// Non-empty segments of `path` with surrounding whitespace removed
+pub fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// `path` with empty segments and stray whitespace removed, e.g. `/a// b/` becomes `a/b`
+pub fn normalize(path: &str) -> String {
+    segments(path).collect::<Vec<_>>().join("/")
+}
+
+/// Oldest folder named `name` directly inside `parent_id`
+async fn find_child(
+    client: &GoogleClient,
+    parent_id: &str,
+    name: &str,
+) -> Result<Option<DriveFolder>, GopenerError> {
+    let page = client
+        .get_page::<DriveFolder>(&child_endpoint(parent_id, name), "files", None)
+        .await?;
+    Ok(page.items.into_iter().next())
+}
+
+fn child_endpoint(parent_id: &str, name: &str) -> String {
+    let query = folders()
+        .and(Query::in_parents(parent_id))
+        .and(Query::name_equals(name));
+    let corpora = if parent_id == MY_DRIVE_ROOT {
+        "corpora=user"
+    } else {
+        "corpora=allDrives"
+    };
+    folder_query_endpoint(&query, "createdTime", corpora)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::retry::RetryPolicy;
+    use crate::utils::test_server::{ScriptedResponse, TestServer};
+    use std::time::Duration;
+
+    fn client(server: &TestServer) -> GoogleClient {
+        GoogleClient::with_config(
+            &server.url,
+            "token".to_string(),
+            RetryPolicy {
+                max_attempts: 1,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(1),
+                jitter: false,
+                idempotent: true,
+            },
+        )
+    }
+
+    fn files(ids: &[&str]) -> ScriptedResponse {
+        let files: Vec<_> = ids
+            .iter()
+            .map(|id| serde_json::json!({"id": id, "name": id, "mimeType": "folder"}))
+            .collect();
+        ScriptedResponse::json(200, serde_json::json!({ "files": files }))
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("/Clients// Acme /2026/"), "Clients/Acme/2026");
+        assert_eq!(normalize(" / "), "");
+    }
+
+    #[test]
+    fn test_child_endpoint_picks_oldest_match() {
+        let endpoint = child_endpoint("root", "Bob's");
+
+        assert!(endpoint.contains(&*urlencoding::encode(
+            r"'root' in parents and name = 'Bob\'s'"
+        )));
+        assert!(endpoint.contains("orderBy=createdTime"));
+        assert!(endpoint.contains("corpora=user"));
+        assert!(child_endpoint("f1", "a").contains("corpora=allDrives"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_finds_creates_and_caches() {
+        let server = TestServer::start(vec![
+            // Clients exists twice; the oldest comes first
+            files(&["clients-old", "clients-new"]),
+            // Acme is missing and gets created
+            files(&[]),
+            ScriptedResponse::json(
+                200,
+                serde_json::json!({"id": "acme", "name": "Acme", "mimeType": "folder"}),
+            ),
+        ])
+        .await;
+        let client = client(&server);
+        let cache = FolderPathCache::new();
+
+        let id = cache.resolve(&client, None, "Clients/Acme").await.unwrap();
+        assert_eq!(id, "acme");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2].method, "POST");
+        assert!(requests[2]
+            .body_text()
+            .contains("\"parents\":[\"clients-old\"]"));
+
+        // Resolved segments are not looked up again
+        let again = cache
+            .resolve(&client, None, "/Clients/ Acme/")
+            .await
+            .unwrap();
+        assert_eq!(again, "acme");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_evicted_paths_are_looked_up_again() {
+        let server = TestServer::start(vec![
+            files(&["clients"]),
+            files(&["acme"]),
+            // Acme was deleted; Clients is still there and a new Acme gets created
+            files(&["clients"]),
+            files(&[]),
+            ScriptedResponse::json(
+                200,
+                serde_json::json!({"id": "acme-2", "name": "Acme", "mimeType": "folder"}),
+            ),
+        ])
+        .await;
+        let client = client(&server);
+        let cache = FolderPathCache::new();
+
+        assert_eq!(
+            cache.resolve(&client, None, "Clients/Acme").await.unwrap(),
+            "acme"
+        );
+        cache.evict(None, "Clients/Acme").await;
+
+        let id = cache.resolve(&client, None, "Clients/Acme").await.unwrap();
+        assert_eq!(id, "acme-2");
+        assert_eq!(server.requests().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_empty_path_is_the_base() {
+        let server = TestServer::start(vec![]).await;
+        let cache = FolderPathCache::new();
+
+        let client = client(&server);
+        assert_eq!(cache.resolve(&client, None, "").await.unwrap(), "root");
+        assert_eq!(
+            cache.resolve(&client, Some("drive-1"), "/").await.unwrap(),
+            "drive-1"
+        );
+        assert!(server.requests().is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+//! Typed builder for Drive `files.list` search queries.
+//!
+//! Every value is quoted and escaped here, so an ID or name containing a quote
+//! can't end the string early and change the meaning of the query.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Search query for the Drive `q` parameter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query(Node);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Term(String),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+impl Query {
+    /// Items with exactly this MIME type
+    pub fn mime_type(mime_type: &str) -> Self {
+        Self::term(format!("mimeType = {}", quote(mime_type)))
+    }
+
+    /// Items directly inside the folder `parent_id`
+    pub fn in_parents(parent_id: &str) -> Self {
+        Self::term(format!("{} in parents", quote(parent_id)))
+    }
+
+    /// Items whose name contains `name`
+    pub fn name_contains(name: &str) -> Self {
+        Self::term(format!("name contains {}", quote(name)))
+    }
+
+    /// Items named exactly `name`
+    pub fn name_equals(name: &str) -> Self {
+        Self::term(format!("name = {}", quote(name)))
+    }
+
+    /// Items in, or not in, the trash
+    pub fn trashed(trashed: bool) -> Self {
+        Self::term(format!("trashed = {}", trashed))
+    }
+
+    /// Items shared with the user by someone else
+    pub fn shared_with_me() -> Self {
+        Self::term("sharedWithMe".to_string())
+    }
+
+    /// Items the user has starred
+    pub fn starred() -> Self {
+        Self::term("starred = true".to_string())
+    }
+
+    /// Items carrying the app property `key` set to `value`
+    pub fn app_property(key: &str, value: &str) -> Self {
+        Self::term(format!(
+            "appProperties has {{ key={} and value={} }}",
+            quote(key),
+            quote(value)
+        ))
+    }
+
+    /// Items modified after `time`
+    pub fn modified_after(time: SystemTime) -> Self {
+        Self::term(format!("modifiedTime > {}", quote(&rfc3339(time))))
+    }
+
+    /// Items the user last opened after `time`
+    pub fn viewed_by_me_after(time: SystemTime) -> Self {
+        Self::term(format!("viewedByMeTime > {}", quote(&rfc3339(time))))
+    }
+
+    /// Items modified before `time`
+    pub fn modified_before(time: SystemTime) -> Self {
+        Self::term(format!("modifiedTime < {}", quote(&rfc3339(time))))
+    }
+
+    /// Items matching both this query and `other`
+    pub fn and(self, other: Query) -> Self {
+        Query(Node::And(join(self, other, |node| match node {
+            Node::And(queries) => Ok(queries),
+            node => Err(node),
+        })))
+    }
+
+    /// Items matching this query, `other`, or both
+    pub fn or(self, other: Query) -> Self {
+        Query(Node::Or(join(self, other, |node| match node {
+            Node::Or(queries) => Ok(queries),
+            node => Err(node),
+        })))
+    }
+
+    fn term(term: String) -> Self {
+        Query(Node::Term(term))
+    }
+}
+
+/// Operands of a chain of the same operator, flattened into one list
+fn join(
+    left: Query,
+    right: Query,
+    same_operator: fn(Node) -> Result<Vec<Query>, Node>,
+) -> Vec<Query> {
+    let mut queries = Vec::new();
+    for query in [left, right] {
+        match same_operator(query.0) {
+            Ok(operands) => queries.extend(operands),
+            Err(node) => queries.push(Query(node)),
+        }
+    }
+    queries
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (queries, operator) = match &self.0 {
+            Node::Term(term) => return f.write_str(term),
+            Node::And(queries) => (queries, " and "),
+            Node::Or(queries) => (queries, " or "),
+        };
+
+        for (i, query) in queries.iter().enumerate() {
+            if i > 0 {
+                f.write_str(operator)?;
+            }
+            // Operands are never the same operator, so anything compound needs grouping
+            match query.0 {
+                Node::Term(_) => write!(f, "{}", query)?,
+                _ => write!(f, "({})", query)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Single-quoted string literal with quotes and backslashes escaped
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// UTC timestamp in the RFC 3339 form Drive expects, e.g. `2024-01-31T09:30:00Z`
+fn rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_values_are_quoted_and_escaped() {
+        assert_eq!(
+            Query::name_contains("Bob's").to_string(),
+            r"name contains 'Bob\'s'"
+        );
+        assert_eq!(
+            Query::in_parents(r"a\' in parents or '").to_string(),
+            r"'a\\\' in parents or \'' in parents"
+        );
+        assert_eq!(
+            Query::name_equals(r"C:\Reports").to_string(),
+            r"name = 'C:\\Reports'"
+        );
+    }
+
+    #[test]
+    fn test_and_chains_are_flattened() {
+        let query = Query::mime_type("application/vnd.google-apps.folder")
+            .and(Query::trashed(false))
+            .and(Query::in_parents("root"));
+
+        assert_eq!(
+            query.to_string(),
+            "mimeType = 'application/vnd.google-apps.folder' and trashed = false \
+             and 'root' in parents"
+        );
+    }
+
+    #[test]
+    fn test_mixed_operators_are_grouped() {
+        let query = Query::trashed(false).and(
+            Query::name_equals("a")
+                .or(Query::name_equals("b"))
+                .or(Query::name_equals("c")),
+        );
+        assert_eq!(
+            query.to_string(),
+            "trashed = false and (name = 'a' or name = 'b' or name = 'c')"
+        );
+
+        let query = Query::starred().or(Query::shared_with_me().and(Query::trashed(false)));
+        assert_eq!(
+            query.to_string(),
+            "starred = true or (sharedWithMe and trashed = false)"
+        );
+    }
+
+    #[test]
+    fn test_app_property() {
+        assert_eq!(
+            Query::app_property("sourcePath", "/tmp/it's.docx").to_string(),
+            r"appProperties has { key='sourcePath' and value='/tmp/it\'s.docx' }"
+        );
+    }
+
+    #[test]
+    fn test_modified_time_is_rfc3339_utc() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_706_693_400);
+        assert_eq!(
+            Query::modified_after(time).to_string(),
+            "modifiedTime > '2024-01-31T09:30:00Z'"
+        );
+        assert_eq!(
+            Query::modified_before(UNIX_EPOCH + Duration::from_secs(951_782_400)).to_string(),
+            "modifiedTime < '2000-02-29T00:00:00Z'"
+        );
+        assert_eq!(
+            Query::viewed_by_me_after(time).to_string(),
+            "viewedByMeTime > '2024-01-31T09:30:00Z'"
+        );
+    }
+}
@@ -0,0 +1,836 @@
+use crate::error::GopenerError;
+use crate::utils::retry::{self, RetryPolicy};
+use bytes::Bytes;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// Files at or above this size are sent through a resumable session
+pub const RESUMABLE_THRESHOLD: u64 = 5 * 1024 * 1024;
+
+/// Fields requested for the uploaded file
+const UPLOAD_FIELDS: &str = "id,name,webViewLink,mimeType";
+
+/// Request bodies are handed to the connection in pieces of this size so progress
+/// can be reported while a request is being written
+const PROGRESS_PIECE_SIZE: usize = 64 * 1024;
+
+/// Receives the number of file bytes handed to the connection so far
+pub type ProgressCallback = Arc<dyn Fn(u64) + Send + Sync>;
+
+/// File metadata returned by Drive once an upload completes
+#[derive(Debug, Deserialize, Clone)]
+pub struct UploadedFile {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "webViewLink")]
+    pub web_view_link: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+}
+
+/// What an upload writes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadTarget<'a> {
+    /// Create a new file described by the metadata
+    NewFile,
+    /// Replace the content of an existing file, adding a revision
+    Revision(&'a str),
+}
+
+impl UploadTarget<'_> {
+    /// Creating a file must not happen twice, so its request is only repeated when
+    /// Drive can't have acted on it; replacing content again is harmless
+    fn retry_policy(&self, retry: &RetryPolicy) -> RetryPolicy {
+        match self {
+            UploadTarget::NewFile => retry.non_idempotent(),
+            UploadTarget::Revision(_) => retry.clone(),
+        }
+    }
+
+    /// Request to `upload_url` with `query`, sent to the file being updated when there is one
+    fn request(&self, client: &Client, upload_url: &str, query: &str) -> reqwest::RequestBuilder {
+        match self {
+            UploadTarget::NewFile => client.post(format!("{}?{}", upload_url, query)),
+            UploadTarget::Revision(file_id) => client.patch(format!(
+                "{}/{}?{}",
+                upload_url,
+                urlencoding::encode(file_id),
+                query
+            )),
+        }
+    }
+}
+
+/// Tuning for resumable uploads
+#[derive(Debug, Clone)]
+pub struct ResumableConfig {
+    /// Bytes per request; Drive requires a multiple of 256 KiB except for the last chunk
+    pub chunk_size: u64,
+    /// Backoff for failed chunks; `max_attempts` counts consecutive failures
+    pub retry: RetryPolicy,
+}
+
+impl Default for ResumableConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 8 * 1024 * 1024,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// State of a resumable session as reported by Drive
+#[derive(Debug)]
+enum SessionStatus {
+    /// Bytes `0..offset` are committed; the upload continues from `offset`
+    Incomplete(u64),
+    Complete(UploadedFile),
+}
+
+/// Query of an upload request; `ocr_language` hints the language of text in scans
+fn upload_query(upload_type: &str, ocr_language: Option<&str>) -> String {
+    let mut query = format!(
+        "uploadType={}&fields={}&supportsAllDrives=true",
+        upload_type, UPLOAD_FIELDS
+    );
+    if let Some(language) = ocr_language {
+        query.push_str(&format!("&ocrLanguage={}", urlencoding::encode(language)));
+    }
+    query
+}
+
+/// Upload a small file in a single `uploadType=multipart` request
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_multipart(
+    client: &Client,
+    upload_url: &str,
+    target: UploadTarget<'_>,
+    access_token: &str,
+    metadata: &serde_json::Value,
+    ocr_language: Option<&str>,
+    path: &Path,
+    content_type: &str,
+    retry: &RetryPolicy,
+    on_progress: ProgressCallback,
+) -> Result<UploadedFile, GopenerError> {
+    let file_contents = tokio::fs::read(path)
+        .await
+        .map_err(|e| GopenerError::Io(format!("Failed to read file: {}", e)))?;
+
+    let metadata_json =
+        serde_json::to_string(metadata).map_err(|e| GopenerError::Internal(e.to_string()))?;
+
+    // Build multipart body manually for Google Drive API
+    let boundary = "gopener_boundary_12345";
+    let mut head = Vec::new();
+
+    // Metadata part
+    head.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+    head.extend_from_slice(b"Content-Type: application/json; charset=UTF-8\r\n\r\n");
+    head.extend_from_slice(metadata_json.as_bytes());
+    head.extend_from_slice(b"\r\n");
+
+    // File part header; the content itself is streamed in between
+    head.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+    head.extend_from_slice(format!("Content-Type: {}\r\n\r\n", content_type).as_bytes());
+
+    // End boundary
+    let tail = format!("\r\n--{}--", boundary).into_bytes();
+
+    let content_length = head.len() + file_contents.len() + tail.len();
+    let (head, content, tail) = (
+        Bytes::from(head),
+        Bytes::from(file_contents),
+        Bytes::from(tail),
+    );
+    let query = upload_query("multipart", ocr_language);
+
+    // A retried request streams the whole body again, so progress restarts from zero
+    let response = target
+        .retry_policy(retry)
+        .send(|| {
+            target
+                .request(client, upload_url, &query)
+                .header("Authorization", format!("Bearer {}", access_token))
+                .header(
+                    "Content-Type",
+                    format!("multipart/related; boundary={}", boundary),
+                )
+                .header("Content-Length", content_length)
+                .body(progress_body(
+                    head.clone(),
+                    content.clone(),
+                    tail.clone(),
+                    0,
+                    on_progress.clone(),
+                ))
+        })
+        .await
+        .map_err(|e| GopenerError::Network(format!("Upload failed: {}", e)))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(GopenerError::google("Upload failed", status, &error_text));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| GopenerError::Internal(format!("Failed to parse response: {}", e)))
+}
+
+/// Open a resumable upload session and return its session URI
+#[allow(clippy::too_many_arguments)]
+pub async fn start_resumable_session(
+    client: &Client,
+    upload_url: &str,
+    target: UploadTarget<'_>,
+    access_token: &str,
+    metadata: &serde_json::Value,
+    ocr_language: Option<&str>,
+    content_type: &str,
+    total_size: u64,
+    retry: &RetryPolicy,
+) -> Result<String, GopenerError> {
+    let query = upload_query("resumable", ocr_language);
+    let response = target
+        .retry_policy(retry)
+        .send(|| {
+            target
+                .request(client, upload_url, &query)
+                .header("Authorization", format!("Bearer {}", access_token))
+                .header("X-Upload-Content-Type", content_type)
+                .header("X-Upload-Content-Length", total_size)
+                .json(metadata)
+        })
+        .await
+        .map_err(|e| GopenerError::Network(format!("Failed to start upload session: {}", e)))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(GopenerError::google(
+            "Failed to start upload session",
+            status,
+            &error_text,
+        ));
+    }
+
+    response
+        .headers()
+        .get("Location")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+        .ok_or_else(|| {
+            GopenerError::Internal("Upload session response had no Location header".to_string())
+        })
+}
+
+/// Discard a resumable session so Drive drops the bytes uploaded so far.
+///
+/// Drive answers a cancelled session with 499; an already expired session is
+/// not an error either.
+pub async fn cancel_resumable_session(
+    client: &Client,
+    session_url: &str,
+) -> Result<(), GopenerError> {
+    let response =
+        client.delete(session_url).send().await.map_err(|e| {
+            GopenerError::Network(format!("Failed to cancel upload session: {}", e))
+        })?;
+
+    let status = response.status();
+    if status.is_success() || matches!(status.as_u16(), 404 | 410 | 499) {
+        Ok(())
+    } else {
+        let error_text = response.text().await.unwrap_or_default();
+        Err(GopenerError::google(
+            "Failed to cancel upload session",
+            status,
+            &error_text,
+        ))
+    }
+}
+
+/// Stream a file to an open resumable session in fixed-size chunks.
+///
+/// After a failed chunk the session is asked how many bytes it has committed and
+/// the upload continues from there. `on_progress` follows the bytes written
+/// within each chunk and snaps back to the committed offset after a failure.
+pub async fn upload_resumable(
+    client: &Client,
+    session_url: &str,
+    path: &Path,
+    total_size: u64,
+    config: &ResumableConfig,
+    on_progress: ProgressCallback,
+) -> Result<UploadedFile, GopenerError> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| GopenerError::Io(format!("Failed to open file: {}", e)))?;
+
+    let mut offset = 0;
+    let mut failed_attempts = 0;
+
+    loop {
+        let chunk = read_chunk(&mut file, offset, config.chunk_size, total_size)
+            .await
+            .map_err(|e| GopenerError::Io(format!("Failed to read file: {}", e)))?;
+        let chunk_len = chunk.len() as u64;
+
+        let result = client
+            .put(session_url)
+            .header("Content-Length", chunk_len)
+            .header(
+                "Content-Range",
+                content_range(offset, chunk_len, total_size),
+            )
+            .body(progress_body(
+                Bytes::new(),
+                Bytes::from(chunk),
+                Bytes::new(),
+                offset,
+                on_progress.clone(),
+            ))
+            .send()
+            .await;
+
+        let (error, retry_after) = match result {
+            Ok(response) => match session_status(response).await {
+                Ok(SessionStatus::Complete(file)) => {
+                    on_progress(total_size);
+                    return Ok(file);
+                }
+                Ok(SessionStatus::Incomplete(committed)) if committed > offset => {
+                    offset = committed;
+                    failed_attempts = 0;
+                    on_progress(offset);
+                    continue;
+                }
+                Ok(SessionStatus::Incomplete(_)) => (
+                    GopenerError::Network("Upload session did not accept the chunk".to_string()),
+                    None,
+                ),
+                Err(ChunkError::Fatal(e)) => return Err(e),
+                Err(ChunkError::Retryable(e, retry_after)) => (e, retry_after),
+            },
+            Err(e) => (
+                GopenerError::Network(format!("Upload interrupted: {}", e)),
+                None,
+            ),
+        };
+
+        failed_attempts += 1;
+        if failed_attempts >= config.retry.max_attempts {
+            return Err(error);
+        }
+        tokio::time::sleep(config.retry.delay(failed_attempts, retry_after)).await;
+
+        // Ask the session what it actually received before sending more
+        match query_offset(client, session_url, total_size).await {
+            Ok(SessionStatus::Complete(file)) => {
+                on_progress(total_size);
+                return Ok(file);
+            }
+            Ok(SessionStatus::Incomplete(committed)) => {
+                offset = committed;
+                on_progress(offset);
+            }
+            Err(ChunkError::Fatal(e)) => return Err(e),
+            // Keep the previous offset and let the next chunk attempt count against the limit
+            Err(ChunkError::Retryable(..)) => {}
+        }
+    }
+}
+
+enum ChunkError {
+    /// Worth another attempt, after the server-requested delay if there is one
+    Retryable(GopenerError, Option<Duration>),
+    Fatal(GopenerError),
+}
+
+/// Ask a resumable session how many bytes it has committed
+async fn query_offset(
+    client: &Client,
+    session_url: &str,
+    total_size: u64,
+) -> Result<SessionStatus, ChunkError> {
+    let response = client
+        .put(session_url)
+        .header("Content-Length", 0)
+        .header("Content-Range", format!("bytes */{}", total_size))
+        .send()
+        .await
+        .map_err(|e| {
+            ChunkError::Retryable(
+                GopenerError::Network(format!("Failed to query upload status: {}", e)),
+                None,
+            )
+        })?;
+
+    session_status(response).await
+}
+
+/// Interpret a response from a resumable session URI
+async fn session_status(response: reqwest::Response) -> Result<SessionStatus, ChunkError> {
+    let status = response.status();
+
+    if status.is_success() {
+        return response
+            .json()
+            .await
+            .map(SessionStatus::Complete)
+            .map_err(|e| {
+                ChunkError::Fatal(GopenerError::Internal(format!(
+                    "Failed to parse response: {}",
+                    e
+                )))
+            });
+    }
+
+    if status == StatusCode::PERMANENT_REDIRECT {
+        let committed = response
+            .headers()
+            .get("Range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_committed_range)
+            .unwrap_or(0);
+        return Ok(SessionStatus::Incomplete(committed));
+    }
+
+    let retry_after = retry::parse_retry_after(response.headers());
+    let error_text = response.text().await.unwrap_or_default();
+
+    if status == StatusCode::NOT_FOUND || status == StatusCode::GONE {
+        return Err(ChunkError::Fatal(GopenerError::SessionExpired(error_text)));
+    }
+
+    let error = GopenerError::google("Upload failed", status, &error_text);
+    if retry::is_retryable_status(status, &error_text) {
+        return Err(ChunkError::Retryable(error, retry_after));
+    }
+
+    Err(ChunkError::Fatal(error))
+}
+
+/// Streaming request body that reports `base` plus the content bytes already
+/// written each time the connection pulls the next piece
+fn progress_body(
+    head: Bytes,
+    content: Bytes,
+    tail: Bytes,
+    base: u64,
+    on_progress: ProgressCallback,
+) -> reqwest::Body {
+    let content_len = content.len();
+    let mut pieces = vec![(head, 0)];
+    for start in (0..content_len).step_by(PROGRESS_PIECE_SIZE) {
+        let end = (start + PROGRESS_PIECE_SIZE).min(content_len);
+        pieces.push((content.slice(start..end), start as u64));
+    }
+    pieces.push((tail, content_len as u64));
+
+    let stream =
+        futures_util::stream::iter(pieces.into_iter().filter_map(move |(piece, sent_before)| {
+            // Pulling a piece means everything before it has been written out
+            on_progress(base + sent_before);
+            (!piece.is_empty()).then_some(Ok::<_, std::io::Error>(piece))
+        }));
+
+    reqwest::Body::wrap_stream(stream)
+}
+
+/// Read up to `chunk_size` bytes starting at `offset`
+async fn read_chunk(
+    file: &mut tokio::fs::File,
+    offset: u64,
+    chunk_size: u64,
+    total_size: u64,
+) -> std::io::Result<Vec<u8>> {
+    let len = chunk_size.min(total_size.saturating_sub(offset)) as usize;
+    let mut buffer = vec![0; len];
+    file.seek(std::io::SeekFrom::Start(offset)).await?;
+    file.read_exact(&mut buffer).await?;
+    Ok(buffer)
+}
+
+/// `Content-Range` header value for a chunk
+fn content_range(offset: u64, len: u64, total_size: u64) -> String {
+    if len == 0 {
+        format!("bytes */{}", total_size)
+    } else {
+        format!("bytes {}-{}/{}", offset, offset + len - 1, total_size)
+    }
+}
+
+/// Parse a `Range: bytes=0-N` header into the next offset to send (`N + 1`)
+fn parse_committed_range(range: &str) -> Option<u64> {
+    let (_, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    end.trim().parse::<u64>().ok().map(|end| end + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{ScriptedResponse, TestServer};
+
+    const KIB: u64 = 1024;
+
+    fn write_temp_file(name: &str, len: usize) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let contents: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn recorder() -> (ProgressCallback, Arc<std::sync::Mutex<Vec<u64>>>) {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let callback: ProgressCallback = Arc::new(move |sent| sink.lock().unwrap().push(sent));
+        (callback, seen)
+    }
+
+    fn ignore_progress() -> ProgressCallback {
+        Arc::new(|_| {})
+    }
+
+    fn test_config() -> ResumableConfig {
+        ResumableConfig {
+            chunk_size: 256 * KIB,
+            retry: fast_retry(3),
+        }
+    }
+
+    fn fast_retry(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+            jitter: false,
+            idempotent: true,
+        }
+    }
+
+    fn done() -> ScriptedResponse {
+        ScriptedResponse::json(
+            200,
+            serde_json::json!({
+                "id": "file-1",
+                "name": "big.xlsx",
+                "webViewLink": "https://docs.google.com/spreadsheets/d/file-1/edit",
+                "mimeType": "application/vnd.google-apps.spreadsheet"
+            }),
+        )
+    }
+
+    fn committed(end: u64) -> ScriptedResponse {
+        ScriptedResponse::new(308, "").header("Range", &format!("bytes=0-{}", end))
+    }
+
+    #[test]
+    fn test_parse_committed_range() {
+        assert_eq!(parse_committed_range("bytes=0-262143"), Some(262144));
+        assert_eq!(parse_committed_range("bytes=0-0"), Some(1));
+        assert_eq!(parse_committed_range("0-10"), None);
+        assert_eq!(parse_committed_range("bytes=0-"), None);
+    }
+
+    #[test]
+    fn test_content_range() {
+        assert_eq!(content_range(0, 100, 300), "bytes 0-99/300");
+        assert_eq!(content_range(200, 100, 300), "bytes 200-299/300");
+        assert_eq!(content_range(300, 0, 300), "bytes */300");
+    }
+
+    #[test]
+    fn test_default_chunk_size_is_multiple_of_256_kib() {
+        assert_eq!(ResumableConfig::default().chunk_size % (256 * KIB), 0);
+    }
+
+    #[tokio::test]
+    async fn test_start_resumable_session_returns_location() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::new(200, "").header("Location", "http://upload/session-1")
+        ])
+        .await;
+
+        let session = start_resumable_session(
+            &Client::new(),
+            &server.url,
+            UploadTarget::NewFile,
+            "token",
+            &serde_json::json!({"name": "big.xlsx"}),
+            None,
+            "application/vnd.ms-excel",
+            1234,
+            &fast_retry(1),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(session, "http://upload/session-1");
+        let request = &server.requests()[0];
+        assert!(request.path.contains("uploadType=resumable"));
+        assert!(request.path.contains("supportsAllDrives=true"));
+        assert!(!request.path.contains("ocrLanguage"));
+        assert_eq!(request.header("x-upload-content-length"), Some("1234"));
+        assert_eq!(request.header("authorization"), Some("Bearer token"));
+        assert!(request.body_text().contains("big.xlsx"));
+    }
+
+    #[tokio::test]
+    async fn test_revision_uploads_patch_the_existing_file() {
+        let path = write_temp_file("gopener_revision.docx", 10 * KIB as usize);
+        let server = TestServer::start(vec![
+            done(),
+            ScriptedResponse::new(200, "").header("Location", "http://upload/session-2"),
+        ])
+        .await;
+        let client = Client::new();
+        let target = UploadTarget::Revision("file 1");
+
+        upload_multipart(
+            &client,
+            &server.url,
+            target,
+            "token",
+            &serde_json::json!({}),
+            None,
+            &path,
+            "application/octet-stream",
+            &fast_retry(1),
+            Arc::new(|_| {}),
+        )
+        .await
+        .unwrap();
+        start_resumable_session(
+            &client,
+            &server.url,
+            target,
+            "token",
+            &serde_json::json!({}),
+            None,
+            "application/octet-stream",
+            10 * KIB,
+            &fast_retry(1),
+        )
+        .await
+        .unwrap();
+
+        for request in server.requests() {
+            assert_eq!(request.method, "PATCH");
+            assert!(request.path.starts_with("/file%201?uploadType="));
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_resumable_session_deletes_session() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::new(499, ""),
+            ScriptedResponse::new(404, ""),
+            ScriptedResponse::new(500, "backend error"),
+        ])
+        .await;
+        let client = Client::new();
+        let session = format!("{}/upload/session-1", server.url);
+
+        assert!(cancel_resumable_session(&client, &session).await.is_ok());
+        assert!(cancel_resumable_session(&client, &session).await.is_ok());
+        assert!(cancel_resumable_session(&client, &session).await.is_err());
+
+        let request = &server.requests()[0];
+        assert_eq!(request.method, "DELETE");
+        assert_eq!(request.path, "/upload/session-1");
+    }
+
+    #[tokio::test]
+    async fn test_upload_resumable_sends_chunks_in_order() {
+        let path = write_temp_file("gopener_resumable_chunks.bin", 600 * KIB as usize);
+        let server = TestServer::start(vec![
+            committed(256 * KIB - 1),
+            committed(512 * KIB - 1),
+            done(),
+        ])
+        .await;
+
+        let (on_progress, progress) = recorder();
+        let file = upload_resumable(
+            &Client::new(),
+            &server.url,
+            &path,
+            600 * KIB,
+            &test_config(),
+            on_progress,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(file.id, "file-1");
+        let ranges: Vec<_> = server
+            .requests()
+            .iter()
+            .map(|r| r.header("content-range").unwrap().to_string())
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                "bytes 0-262143/614400",
+                "bytes 262144-524287/614400",
+                "bytes 524288-614399/614400",
+            ]
+        );
+        // Progress moves within each chunk, not just at chunk boundaries
+        let progress = progress.lock().unwrap().clone();
+        assert!(progress.windows(2).all(|w| w[0] <= w[1]));
+        assert!(progress.contains(&(64 * KIB)));
+        assert!(progress.contains(&(320 * KIB)));
+        assert_eq!(progress.last(), Some(&(600 * KIB)));
+
+        let body: Vec<u8> = server
+            .requests()
+            .iter()
+            .flat_map(|r| r.body.clone())
+            .collect();
+        assert_eq!(body, std::fs::read(&path).unwrap());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upload_resumable_resumes_from_committed_offset() {
+        let path = write_temp_file("gopener_resumable_resume.bin", 600 * KIB as usize);
+        let server = TestServer::start(vec![
+            committed(256 * KIB - 1),
+            // Second chunk fails; the session only kept part of it
+            ScriptedResponse::new(503, "backend error"),
+            committed(300 * KIB - 1),
+            committed(556 * KIB - 1),
+            done(),
+        ])
+        .await;
+
+        upload_resumable(
+            &Client::new(),
+            &server.url,
+            &path,
+            600 * KIB,
+            &test_config(),
+            ignore_progress(),
+        )
+        .await
+        .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 5);
+        assert_eq!(requests[2].header("content-range"), Some("bytes */614400"));
+        assert!(requests[2].body.is_empty());
+        assert_eq!(
+            requests[3].header("content-range"),
+            Some("bytes 307200-569343/614400")
+        );
+        assert_eq!(
+            requests[4].header("content-range"),
+            Some("bytes 569344-614399/614400")
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upload_resumable_gives_up_after_max_attempts() {
+        let path = write_temp_file("gopener_resumable_give_up.bin", 100);
+        let server = TestServer::start(vec![
+            ScriptedResponse::new(500, "down"),
+            ScriptedResponse::new(308, ""),
+            ScriptedResponse::new(500, "down"),
+            ScriptedResponse::new(308, ""),
+            ScriptedResponse::new(500, "still down"),
+        ])
+        .await;
+
+        let result = upload_resumable(
+            &Client::new(),
+            &server.url,
+            &path,
+            100,
+            &test_config(),
+            ignore_progress(),
+        )
+        .await;
+
+        let err = result.unwrap_err();
+        assert_eq!(err.to_string(), "Upload failed: still down");
+        assert!(err.retryable());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upload_resumable_stops_on_expired_session() {
+        let path = write_temp_file("gopener_resumable_expired.bin", 100);
+        let server = TestServer::start(vec![ScriptedResponse::new(404, "no such session")]).await;
+
+        let result = upload_resumable(
+            &Client::new(),
+            &server.url,
+            &path,
+            100,
+            &test_config(),
+            ignore_progress(),
+        )
+        .await;
+
+        let err = result.unwrap_err();
+        assert_eq!(err.code(), "upload_session_expired");
+        assert!(err.to_string().starts_with("Upload session expired"));
+        assert_eq!(server.requests().len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upload_multipart_sends_metadata_and_content() {
+        let path = write_temp_file("gopener_multipart.docx", 150 * KIB as usize);
+        let server = TestServer::start(vec![done()]).await;
+        let (on_progress, progress) = recorder();
+
+        let file = upload_multipart(
+            &Client::new(),
+            &server.url,
+            UploadTarget::NewFile,
+            "token",
+            &serde_json::json!({"name": "small.docx"}),
+            Some("en"),
+            &path,
+            "application/octet-stream",
+            &fast_retry(1),
+            on_progress,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(file.name, "big.xlsx");
+        let progress = progress.lock().unwrap().clone();
+        assert_eq!(progress, vec![0, 0, 64 * KIB, 128 * KIB, 150 * KIB]);
+
+        let request = &server.requests()[0];
+        assert!(request.path.contains("uploadType=multipart"));
+        assert!(request.path.contains("supportsAllDrives=true"));
+        assert!(request.path.contains("&ocrLanguage=en"));
+        assert!(request
+            .header("content-type")
+            .unwrap()
+            .starts_with("multipart/related"));
+        assert!(request.body_text().contains("small.docx"));
+        assert!(request.body_text().ends_with("--gopener_boundary_12345--"));
+        let content_length: usize = request.header("content-length").unwrap().parse().unwrap();
+        assert_eq!(content_length, request.body.len());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
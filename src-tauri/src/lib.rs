@@ -1,5 +1,6 @@
 pub mod config;
 mod commands;
+mod error;
 mod google;
 mod utils;
 
@@ -10,6 +11,9 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .manage(commands::jobs::UploadManager::new())
+        .manage(google::drive::folder_path::FolderPathCache::new())
+        .manage(commands::upload_db::UploadHistory::new())
         .setup(|app| {
             // Check for file arguments passed via CLI or file association
             let args: Vec<String> = std::env::args().collect();
@@ -32,13 +36,22 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             // Auth commands
             commands::auth::get_auth_url,
+            commands::auth::start_sign_in,
+            commands::auth::cancel_sign_in,
             commands::auth::exchange_code,
             commands::auth::refresh_token,
             commands::auth::sign_out,
             commands::auth::check_auth,
             // Upload commands
             commands::upload::upload_file,
-            commands::upload::get_upload_progress,
+            commands::upload::upload_files,
+            commands::folder_upload::upload_folder,
+            commands::jobs::list_upload_jobs,
+            commands::jobs::get_upload_job,
+            commands::jobs::cancel_upload,
+            commands::upload_db::find_uploads_by_path,
+            commands::upload_db::find_upload_by_drive_id,
+            commands::upload_db::list_uploads_in_folder,
             // Storage commands
             commands::storage::get_settings,
             commands::storage::save_settings,
@@ -50,8 +63,13 @@ pub fn run() {
             commands::oauth_config::clear_oauth_config,
             // File association commands
             commands::fileassoc::register_file_associations,
+            commands::formats::get_supported_formats,
             // Google Drive commands
+            google::drive::list_shared_drives,
             google::drive::list_folders,
+            google::drive::list_all_folders,
+            google::drive::list_root_folders,
+            google::drive::search_folders,
             google::drive::create_folder,
         ])
         .run(tauri::generate_context!())
@@ -1,7 +1,24 @@
+use crate::utils::formats::{self, Format};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 
+/// Bytes read from the start of a file to judge whether it is text
+const SNIFF_LENGTH: usize = 8192;
+/// Package parts read while sniffing are a few KB; anything much larger isn't an Office file
+const MAX_PART_LENGTH: u64 = 1024 * 1024;
+
+/// Share of a conversion limit past which the user is warned
+const NEAR_LIMIT_PERCENT: u64 = 90;
+
+const CFB_SIGNATURE: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+
 /// Supported file types and their Google conversion targets
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum GoogleFileType {
     Document,
     Spreadsheet,
@@ -18,6 +35,13 @@ impl GoogleFileType {
         }
     }
 
+    /// The Google format a Drive file is in, if it was converted to one
+    pub fn from_google_mime_type(mime_type: &str) -> Option<Self> {
+        [Self::Document, Self::Spreadsheet, Self::Presentation]
+            .into_iter()
+            .find(|t| t.google_mime_type() == mime_type)
+    }
+
     /// Get the display name for this file type
     pub fn display_name(&self) -> &'static str {
         match self {
@@ -40,47 +64,659 @@ impl GoogleFileType {
 
 /// Detect the file type from a file path
 pub fn detect_file_type(path: &Path) -> Option<GoogleFileType> {
-    let extension = path.extension()?.to_str()?.to_lowercase();
-
-    match extension.as_str() {
-        // Google Docs
-        "doc" | "docx" | "odt" | "rtf" | "txt" => Some(GoogleFileType::Document),
-        // Google Sheets
-        "xls" | "xlsx" | "ods" | "csv" | "tsv" => Some(GoogleFileType::Spreadsheet),
-        // Google Slides
-        "ppt" | "pptx" | "odp" => Some(GoogleFileType::Presentation),
-        _ => None,
-    }
+    formats::by_path(path).map(|format| format.target)
+}
+
+/// Whether Drive converts the file by recognizing text in it (PDFs and images)
+#[cfg(test)]
+pub fn needs_ocr(path: &Path) -> bool {
+    formats::by_path(path).is_some_and(|format| format.ocr)
+}
+
+/// Name of the format a file keeps when uploaded without conversion
+pub fn original_format_name(path: &Path) -> Option<&'static str> {
+    formats::by_path(path).map(|format| format.name)
 }
 
 /// Get the MIME type for a file based on its extension
 pub fn get_mime_type(path: &Path) -> String {
+    // mime_guess doesn't know several Office variants, so supported formats come first
+    if let Some(format) = formats::by_path(path) {
+        return format.mime_type.to_string();
+    }
     mime_guess::from_path(path)
         .first_or_octet_stream()
         .to_string()
 }
 
+/// Format of a file as judged by its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Detected {
+    pub format: &'static Format,
+    /// The extension names a different format than the content holds
+    pub extension_mismatch: bool,
+}
+
+impl Detected {
+    /// Note for the user when the extension lied about the content
+    pub fn warning(&self, path: &Path) -> Option<String> {
+        if !self.extension_mismatch {
+            return None;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        Some(format!(
+            "{} is really a {} file, so it was uploaded as .{}",
+            name, self.format.name, self.format.extension
+        ))
+    }
+}
+
+/// What the first bytes of a file say it is
+#[derive(Debug, PartialEq, Eq)]
+enum Content {
+    /// Source MIME type of a supported format
+    Mime(String),
+    /// Plain text, such as TXT, CSV or TSV
+    Text,
+    Unknown,
+}
+
+/// Identify a file by its content. Formats the content can't tell apart, such
+/// as `.doc` and `.dot`, are settled by the extension. `None` means the content
+/// matches no supported format.
+pub fn sniff_format(path: &Path) -> std::io::Result<Option<Detected>> {
+    let candidates: Vec<&'static Format> = match sniff_content(path)? {
+        Content::Mime(mime_type) => formats::FORMATS
+            .iter()
+            .filter(|format| format.mime_type.eq_ignore_ascii_case(&mime_type))
+            .collect(),
+        Content::Text => formats::FORMATS
+            .iter()
+            .filter(|format| format.mime_type.starts_with("text/"))
+            .collect(),
+        Content::Unknown => Vec::new(),
+    };
+
+    let by_extension = formats::by_path(path);
+    Ok(match by_extension.filter(|format| candidates.contains(format)) {
+        Some(format) => Some(Detected {
+            format,
+            extension_mismatch: false,
+        }),
+        None => candidates.first().map(|&format| Detected {
+            format,
+            extension_mismatch: true,
+        }),
+    })
+}
+
+fn sniff_content(path: &Path) -> std::io::Result<Content> {
+    let mut head = Vec::with_capacity(SNIFF_LENGTH);
+    File::open(path)?
+        .take(SNIFF_LENGTH as u64)
+        .read_to_end(&mut head)?;
+
+    if head.starts_with(CFB_SIGNATURE) {
+        return Ok(sniff_compound_file(path));
+    }
+    if head.starts_with(ZIP_SIGNATURE) {
+        return Ok(sniff_zip(path));
+    }
+
+    let mime_type = if head.starts_with(b"{\\rtf") {
+        "application/rtf"
+    } else if head.starts_with(b"%PDF-") {
+        "application/pdf"
+    } else if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        "image/gif"
+    } else if is_plain_text(&head) {
+        return Ok(Content::Text);
+    } else {
+        return Ok(Content::Unknown);
+    };
+    Ok(Content::Mime(mime_type.to_string()))
+}
+
+/// Legacy Office files are OLE2 compound files told apart by their main stream
+fn sniff_compound_file(path: &Path) -> Content {
+    let Ok(file) = cfb::open(path) else {
+        return Content::Unknown;
+    };
+    let mime_type = if file.is_stream("/WordDocument") {
+        "application/msword"
+    } else if file.is_stream("/Workbook") || file.is_stream("/Book") {
+        "application/vnd.ms-excel"
+    } else if file.is_stream("/PowerPoint Document") {
+        "application/vnd.ms-powerpoint"
+    } else {
+        return Content::Unknown;
+    };
+    Content::Mime(mime_type.to_string())
+}
+
+/// OpenDocument names its type in a `mimetype` entry; OOXML declares the
+/// content type of its main part in `[Content_Types].xml`
+fn sniff_zip(path: &Path) -> Content {
+    let Some(mut archive) = open_zip(path) else {
+        return Content::Unknown;
+    };
+
+    if archive.index_for_name("mimetype").is_some() {
+        return match read_zip_part(&mut archive, "mimetype") {
+            Some(mime_type) => Content::Mime(mime_type.trim().to_string()),
+            None => Content::Unknown,
+        };
+    }
+
+    let Some(content_types) = read_zip_part(&mut archive, "[Content_Types].xml") else {
+        return Content::Unknown;
+    };
+    let content_types = content_types.to_ascii_lowercase();
+
+    // The main part's type is the file's MIME type with `.main+xml` in place of
+    // the version, as in `application/vnd.ms-excel.sheet.macroEnabled.main+xml`
+    formats::FORMATS
+        .iter()
+        .map(|format| format.mime_type)
+        .find(|mime_type| {
+            let base = mime_type.trim_end_matches(".12").to_ascii_lowercase();
+            content_types.contains(&format!("\"{}.main", base))
+        })
+        .map_or(Content::Unknown, |mime_type| {
+            Content::Mime(mime_type.to_string())
+        })
+}
+
+fn open_zip(path: &Path) -> Option<zip::ZipArchive<File>> {
+    zip::ZipArchive::new(File::open(path).ok()?).ok()
+}
+
+fn read_zip_part(archive: &mut zip::ZipArchive<File>, name: &str) -> Option<String> {
+    let mut content = String::new();
+    archive
+        .by_name(name)
+        .ok()?
+        .take(MAX_PART_LENGTH)
+        .read_to_string(&mut content)
+        .ok()?;
+    Some(content)
+}
+
+/// Whether the file is a password-protected Office or OpenDocument file,
+/// which Drive can't convert
+pub fn is_encrypted(path: &Path) -> std::io::Result<bool> {
+    let mut head = Vec::with_capacity(CFB_SIGNATURE.len());
+    File::open(path)?
+        .take(CFB_SIGNATURE.len() as u64)
+        .read_to_end(&mut head)?;
+
+    if head.starts_with(CFB_SIGNATURE) {
+        return Ok(compound_file_encrypted(path));
+    }
+    if head.starts_with(ZIP_SIGNATURE) {
+        // OpenDocument keeps its manifest readable and lists how each part was encrypted
+        let encrypted = open_zip(path)
+            .and_then(|mut archive| read_zip_part(&mut archive, "META-INF/manifest.xml"))
+            .is_some_and(|manifest| manifest.contains("encryption-data"));
+        return Ok(encrypted);
+    }
+    Ok(false)
+}
+
+fn compound_file_encrypted(path: &Path) -> bool {
+    let Ok(mut file) = cfb::open(path) else {
+        return false;
+    };
+
+    // OOXML saved with a password becomes an encrypted package inside a compound file
+    if file.is_stream("/EncryptedPackage") {
+        return true;
+    }
+    if file.is_stream("/WordDocument") {
+        return file
+            .open_stream("/WordDocument")
+            .is_ok_and(|mut stream| word_encrypted(&mut stream));
+    }
+    for name in ["/Workbook", "/Book"] {
+        if file.is_stream(name) {
+            return file
+                .open_stream(name)
+                .is_ok_and(|mut stream| workbook_encrypted(&mut stream));
+        }
+    }
+    if file.is_stream("/CurrentUser") {
+        return file
+            .open_stream("/CurrentUser")
+            .is_ok_and(|mut stream| presentation_encrypted(&mut stream));
+    }
+    false
+}
+
+/// `fEncrypted` in the flags of the Word file information block
+fn word_encrypted(stream: &mut impl Read) -> bool {
+    let mut fib = [0u8; 12];
+    stream.read_exact(&mut fib).is_ok() && u16::from_le_bytes([fib[10], fib[11]]) & 0x0100 != 0
+}
+
+/// A FILEPASS record before the end of the workbook globals
+fn workbook_encrypted(stream: &mut (impl Read + Seek)) -> bool {
+    const FILEPASS: u16 = 0x002F;
+    const EOF: u16 = 0x000A;
+
+    let mut header = [0u8; 4];
+    while stream.read_exact(&mut header).is_ok() {
+        let record = u16::from_le_bytes([header[0], header[1]]);
+        let length = u16::from_le_bytes([header[2], header[3]]);
+        match record {
+            FILEPASS => return true,
+            EOF => return false,
+            _ => {
+                if stream.seek(SeekFrom::Current(length.into())).is_err() {
+                    return false;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// The current user atom carries a different header token once the deck is encrypted
+fn presentation_encrypted(stream: &mut impl Read) -> bool {
+    const ENCRYPTED_TOKEN: u32 = 0xF3D1_C4DF;
+
+    let mut atom = [0u8; 16];
+    stream.read_exact(&mut atom).is_ok()
+        && u32::from_le_bytes([atom[12], atom[13], atom[14], atom[15]]) == ENCRYPTED_TOKEN
+}
+
+/// Text has no control characters besides whitespace; markup such as HTML
+/// saved as `.xls` doesn't count. UTF-16 is recognized by its byte order mark.
+fn is_plain_text(head: &[u8]) -> bool {
+    let text = if let Some(units) = head.strip_prefix(b"\xFF\xFE") {
+        utf16_text(units, u16::from_le_bytes)
+    } else if let Some(units) = head.strip_prefix(b"\xFE\xFF") {
+        utf16_text(units, u16::from_be_bytes)
+    } else {
+        // Bytes of multi-byte UTF-8 sequences are all 0x80 or above, so they
+        // can be checked one at a time
+        let text = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+        Some(text.iter().map(|&b| char::from(b)).collect())
+    };
+    let Some(text) = text else {
+        return false;
+    };
+    let binary = text
+        .iter()
+        .any(|&c| c < ' ' && !matches!(c, '\t' | '\n' | '\r' | '\x0C'));
+    let markup = text
+        .iter()
+        .find(|c| !c.is_ascii_whitespace())
+        .is_some_and(|&c| c == '<');
+    !text.is_empty() && !binary && !markup
+}
+
+/// Decode UTF-16 read with `unit`; `None` when it has unpaired surrogates
+fn utf16_text(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Option<Vec<char>> {
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    let mut decoded = char::decode_utf16(units).peekable();
+    let mut text = Vec::new();
+    while let Some(c) = decoded.next() {
+        match c {
+            Ok(c) => text.push(c),
+            // The head may end halfway through a surrogate pair
+            Err(_) if decoded.peek().is_none() => break,
+            Err(_) => return None,
+        }
+    }
+    Some(text)
+}
+
+/// How a file measures up to the limits of the Google format it converts to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Preflight {
+    Fits,
+    /// Close enough to a limit that Drive may refuse the conversion, or past
+    /// the size it converts well
+    NearLimit(String),
+    /// Over a limit, so Drive would refuse the conversion
+    OverLimit(String),
+}
+
+/// One thing measured against a limit, with how to word it
+struct Measure {
+    value: u64,
+    limit: u64,
+    describe: fn(u64) -> String,
+}
+
+/// Check a file against the conversion limits of `format` before uploading it.
+///
+/// Sheet dimensions and document text are measured inside OOXML and
+/// OpenDocument packages; other files are judged by their size alone.
+pub fn preflight(path: &Path, format: &Format) -> std::io::Result<Preflight> {
+    let limits = &format.limits;
+    let size = std::fs::metadata(path)?.len();
+    let mut measures = vec![Measure {
+        value: size,
+        limit: limits.max_file_size,
+        describe: describe_size,
+    }];
+
+    // A file already too big isn't worth opening
+    if size <= limits.max_file_size {
+        if let (Some(limit), Some(value)) = (limits.max_cells, count_cells(path, format)) {
+            measures.push(Measure {
+                value,
+                limit,
+                describe: |n| format!("{} cells", with_separators(n)),
+            });
+        }
+        if let (Some(limit), Some(value)) =
+            (limits.max_characters, count_characters(path, format))
+        {
+            measures.push(Measure {
+                value,
+                limit,
+                describe: |n| format!("{} characters", with_separators(n)),
+            });
+        }
+    }
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let target = format.target.display_name();
+    if let Some(over) = measures.iter().find(|m| m.value > m.limit) {
+        return Ok(Preflight::OverLimit(format!(
+            "{} has {}, more than the {} {} can convert",
+            name,
+            (over.describe)(over.value),
+            (over.describe)(over.limit),
+            target
+        )));
+    }
+    if let Some(recommended) = limits.recommended_file_size.filter(|&r| size > r) {
+        return Ok(Preflight::NearLimit(format!(
+            "{} has {}, more than the {} {} converts reliably",
+            name,
+            describe_size(size),
+            describe_size(recommended),
+            target
+        )));
+    }
+    if let Some(near) = measures
+        .iter()
+        .find(|m| m.value.saturating_mul(100) >= m.limit.saturating_mul(NEAR_LIMIT_PERCENT))
+    {
+        return Ok(Preflight::NearLimit(format!(
+            "{} has {}, close to the {} {} can convert",
+            name,
+            (near.describe)(near.value),
+            (near.describe)(near.limit),
+            target
+        )));
+    }
+    Ok(Preflight::Fits)
+}
+
+fn describe_size(bytes: u64) -> String {
+    format!("{:.1} MB of data", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// `1234567` as `1,234,567`
+fn with_separators(n: u64) -> String {
+    let digits = n.to_string();
+    let lead = match digits.len() % 3 {
+        0 => 3,
+        rest => rest,
+    };
+    let mut out = digits[..lead].to_string();
+    for group in digits.as_bytes()[lead..].chunks(3) {
+        out.push(',');
+        out.extend(group.iter().map(|&digit| char::from(digit)));
+    }
+    out
+}
+
+/// Cells in use across all sheets, when the package says
+fn count_cells(path: &Path, format: &Format) -> Option<u64> {
+    let mut archive = open_zip(path)?;
+    match format.extension {
+        "xlsx" | "xlsm" | "xltx" => {
+            let sheets: Vec<String> = archive
+                .file_names()
+                .filter(|name| name.starts_with("xl/worksheets/") && name.ends_with(".xml"))
+                .map(str::to_string)
+                .collect();
+            let mut cells = 0;
+            for sheet in sheets {
+                let entry = archive.by_name(&sheet).ok()?;
+                cells = sheet_dimension(BufReader::new(entry))
+                    .unwrap_or(0)
+                    .saturating_add(cells);
+            }
+            Some(cells)
+        }
+        "ods" => {
+            let entry = archive.by_name("content.xml").ok()?;
+            opendocument_cells(BufReader::new(entry)).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Cells in the range a worksheet's `<dimension ref="A1:K250"/>` declares;
+/// `u64::MAX` for a range too large to count
+fn sheet_dimension(reader: impl BufRead) -> Option<u64> {
+    let mut range = None;
+    scan_xml(reader, |item| match item {
+        XmlItem::Tag(tag) if tag_name(tag) == b"dimension" => {
+            range = attribute(tag, b"ref").map(|r| String::from_utf8_lossy(r).into_owned());
+            false
+        }
+        // The dimension comes before the sheet data
+        XmlItem::Tag(tag) if tag_name(tag) == b"sheetData" => false,
+        _ => true,
+    })
+    .ok()?;
+
+    let range = range?;
+    let (start, end) = range.split_once(':').unwrap_or((&range, &range));
+    let (start_col, start_row) = cell_position(start)?;
+    let (end_col, end_row) = cell_position(end)?;
+    let columns = end_col.saturating_sub(start_col).checked_add(1);
+    let rows = end_row.saturating_sub(start_row).checked_add(1);
+    Some(
+        columns
+            .zip(rows)
+            .and_then(|(columns, rows)| columns.checked_mul(rows))
+            .unwrap_or(u64::MAX),
+    )
+}
+
+/// Column and row numbers of a reference such as `AB12`; `None` for numbers
+/// that don't fit in a `u64`
+fn cell_position(reference: &str) -> Option<(u64, u64)> {
+    let split = reference.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = reference.split_at(split);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let column = letters.bytes().try_fold(0u64, |column, letter| {
+        column
+            .checked_mul(26)?
+            .checked_add(u64::from(letter.to_ascii_uppercase() - b'A' + 1))
+    })?;
+    Some((column, digits.parse().ok()?))
+}
+
+/// Cells up to the last row and column holding a value in each table.
+///
+/// Trailing rows and columns that only carry formatting are left out; spreadsheet
+/// apps repeat those to the edge of the sheet.
+fn opendocument_cells(reader: impl BufRead) -> std::io::Result<u64> {
+    let mut total = 0;
+    let (mut rows, mut last_row, mut last_column): (u64, u64, u64) = (0, 0, 0);
+    let (mut repeat_rows, mut column, mut row_last_column): (u64, u64, u64) = (1, 0, 0);
+
+    scan_xml(reader, |item| {
+        let XmlItem::Tag(tag) = item else {
+            return true;
+        };
+        let repeated = |name: &[u8]| {
+            attribute(tag, name)
+                .and_then(|n| std::str::from_utf8(n).ok()?.parse::<u64>().ok())
+                .unwrap_or(1)
+        };
+        let self_closing = tag.ends_with(b"/");
+        match tag_name(tag) {
+            b"table:table" => (rows, last_row, last_column) = (0, 0, 0),
+            b"/table:table" => {
+                total = last_row.saturating_mul(last_column).saturating_add(total)
+            }
+            b"table:table-row" => {
+                (repeat_rows, column, row_last_column) =
+                    (repeated(b"table:number-rows-repeated"), 0, 0);
+                if self_closing {
+                    rows = rows.saturating_add(repeat_rows);
+                }
+            }
+            b"table:table-cell" | b"table:covered-table-cell" => {
+                column = column.saturating_add(repeated(b"table:number-columns-repeated"));
+                if !self_closing || attribute(tag, b"office:value-type").is_some() {
+                    row_last_column = column;
+                }
+            }
+            b"/table:table-row" => {
+                rows = rows.saturating_add(repeat_rows);
+                if row_last_column > 0 {
+                    last_row = rows;
+                    last_column = last_column.max(row_last_column);
+                }
+            }
+            _ => {}
+        }
+        true
+    })?;
+    Ok(total)
+}
+
+/// Characters of text in a word-processing package
+fn count_characters(path: &Path, format: &Format) -> Option<u64> {
+    let mut archive = open_zip(path)?;
+    let (part, text_element): (&str, &[u8]) = match format.extension {
+        "docx" | "docm" | "dotx" => ("word/document.xml", b"w:t"),
+        "odt" => ("content.xml", b"office:body"),
+        _ => return None,
+    };
+    let entry = archive.by_name(part).ok()?;
+
+    let closing = [b"/", text_element].concat();
+    let mut inside = false;
+    let mut characters = 0;
+    scan_xml(BufReader::new(entry), |item| {
+        match item {
+            XmlItem::Tag(tag) if tag_name(tag) == text_element => inside = !tag.ends_with(b"/"),
+            XmlItem::Tag(tag) if tag_name(tag) == closing.as_slice() => inside = false,
+            XmlItem::Text(text) if inside => characters += text_length(text),
+            _ => {}
+        }
+        true
+    })
+    .ok()?;
+    Some(characters)
+}
+
+/// Piece of an XML part, as handed out by `scan_xml`
+enum XmlItem<'a> {
+    /// Everything between `<` and `>`
+    Tag(&'a [u8]),
+    /// Raw text between two tags
+    Text(&'a [u8]),
+}
+
+/// Walk the tags and text of an XML part without building a tree, until
+/// `visit` returns false. Enough for counting; not a validating parser.
+fn scan_xml(
+    mut reader: impl BufRead,
+    mut visit: impl FnMut(XmlItem) -> bool,
+) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'<', &mut buf)? == 0 {
+            return Ok(());
+        }
+        let at_tag = buf.ends_with(b"<");
+        let text = buf.strip_suffix(b"<").unwrap_or(&buf);
+        if !text.is_empty() && !visit(XmlItem::Text(text)) {
+            return Ok(());
+        }
+        if !at_tag {
+            return Ok(());
+        }
+
+        buf.clear();
+        reader.read_until(b'>', &mut buf)?;
+        let tag = buf.strip_suffix(b">").unwrap_or(&buf);
+        if !visit(XmlItem::Tag(tag)) {
+            return Ok(());
+        }
+    }
+}
+
+/// Element name of a tag, with a leading `/` for closing tags
+fn tag_name(tag: &[u8]) -> &[u8] {
+    let end = tag
+        .iter()
+        .position(|&b| b.is_ascii_whitespace() || (b == b'/' && tag.first() != Some(&b'/')))
+        .unwrap_or(tag.len());
+    &tag[..end]
+}
+
+/// Value of a double-quoted attribute
+fn attribute<'a>(tag: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    let pattern = [b" ", name, b"=\""].concat();
+    let start = tag
+        .windows(pattern.len())
+        .position(|window| window == pattern.as_slice())?
+        + pattern.len();
+    let length = tag[start..].iter().position(|&b| b == b'"')?;
+    Some(&tag[start..start + length])
+}
+
+/// Characters in raw XML text, counting an entity such as `&amp;` as one
+fn text_length(text: &[u8]) -> u64 {
+    let mut length = 0;
+    let mut in_entity = false;
+    for &b in text {
+        match b {
+            b'&' => {
+                in_entity = true;
+                length += 1;
+            }
+            b';' if in_entity => in_entity = false,
+            _ if in_entity => {}
+            // Continuation bytes belong to the character before them
+            _ if b & 0xC0 != 0x80 => length += 1,
+            _ => {}
+        }
+    }
+    length
+}
+
 /// Check if a file extension is supported
 #[cfg(test)]
 pub fn is_supported_extension(extension: &str) -> bool {
-    let ext = extension.to_lowercase();
-    matches!(
-        ext.as_str(),
-        "doc" | "docx" | "odt" | "rtf" | "txt" | "xls" | "xlsx" | "ods" | "csv" | "tsv" | "ppt"
-            | "pptx"
-            | "odp"
-    )
+    formats::by_extension(extension).is_some()
 }
 
 /// Get all supported extensions as a list
 #[cfg(test)]
 pub fn supported_extensions() -> Vec<&'static str> {
-    vec![
-        // Documents
-        "doc", "docx", "odt", "rtf", "txt", // Spreadsheets
-        "xls", "xlsx", "ods", "csv", "tsv", // Presentations
-        "ppt", "pptx", "odp",
-    ]
+    formats::FORMATS.iter().map(|format| format.extension).collect()
 }
 
 /// Get file info from a path
@@ -116,6 +752,14 @@ pub fn get_file_info(path: &Path) -> std::io::Result<FileInfo> {
     })
 }
 
+/// Hex SHA-256 of the file's content
+pub fn content_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,6 +779,17 @@ mod tests {
             GoogleFileType::Presentation.google_mime_type(),
             "application/vnd.google-apps.presentation"
         );
+
+        assert_eq!(
+            GoogleFileType::from_google_mime_type("application/vnd.google-apps.spreadsheet"),
+            Some(GoogleFileType::Spreadsheet)
+        );
+        assert_eq!(
+            GoogleFileType::from_google_mime_type(
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            ),
+            None
+        );
     }
 
     #[test]
@@ -153,7 +808,7 @@ mod tests {
 
     #[test]
     fn test_detect_document_types() {
-        for ext in &["doc", "docx", "odt", "rtf", "txt"] {
+        for ext in &["doc", "docx", "odt", "rtf", "txt", "docm", "dotx", "dot"] {
             let filename = format!("test.{}", ext);
             let path = Path::new(&filename);
             assert_eq!(
@@ -167,7 +822,7 @@ mod tests {
 
     #[test]
     fn test_detect_spreadsheet_types() {
-        for ext in &["xls", "xlsx", "ods", "csv", "tsv"] {
+        for ext in &["xls", "xlsx", "ods", "csv", "tsv", "xlsm", "xlsb", "xltx", "xlt"] {
             let filename = format!("test.{}", ext);
             let path = Path::new(&filename);
             assert_eq!(
@@ -181,7 +836,7 @@ mod tests {
 
     #[test]
     fn test_detect_presentation_types() {
-        for ext in &["ppt", "pptx", "odp"] {
+        for ext in &["ppt", "pptx", "odp", "pptm", "potx", "pps", "ppsx"] {
             let filename = format!("test.{}", ext);
             let path = Path::new(&filename);
             assert_eq!(
@@ -193,9 +848,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_detect_scanned_types() {
+        for ext in &["pdf", "png", "jpg", "JPEG", "gif"] {
+            let filename = format!("scan.{}", ext);
+            let path = Path::new(&filename);
+            assert_eq!(detect_file_type(path), Some(GoogleFileType::Document));
+            assert!(needs_ocr(path), "Failed for extension: {}", ext);
+        }
+        assert!(!needs_ocr(Path::new("report.docx")));
+        assert!(!needs_ocr(Path::new("README")));
+    }
+
     #[test]
     fn test_detect_unsupported_types() {
-        for ext in &["pdf", "jpg", "png", "zip", "exe", "mp4"] {
+        for ext in &["svg", "tiff", "zip", "exe", "mp4"] {
             let filename = format!("test.{}", ext);
             let path = Path::new(&filename);
             assert_eq!(
@@ -219,6 +886,23 @@ mod tests {
         assert_eq!(detect_file_type(path), Some(GoogleFileType::Document));
     }
 
+    #[test]
+    fn test_original_format_name() {
+        assert_eq!(
+            original_format_name(Path::new("Budget.XLSX")),
+            Some("Microsoft Excel")
+        );
+        assert_eq!(
+            original_format_name(Path::new("notes.odt")),
+            Some("OpenDocument Text")
+        );
+        assert_eq!(
+            original_format_name(Path::new("scan.jpeg")),
+            Some("JPEG image")
+        );
+        assert_eq!(original_format_name(Path::new("logo.svg")), None);
+    }
+
     #[test]
     fn test_get_mime_type() {
         assert_eq!(
@@ -229,6 +913,27 @@ mod tests {
         assert_eq!(get_mime_type(Path::new("test.csv")), "text/csv");
     }
 
+    #[test]
+    fn test_get_mime_type_office_variants() {
+        assert_eq!(
+            get_mime_type(Path::new("macros.XLSM")),
+            "application/vnd.ms-excel.sheet.macroEnabled.12"
+        );
+        assert_eq!(
+            get_mime_type(Path::new("data.xlsb")),
+            "application/vnd.ms-excel.sheet.binary.macroEnabled.12"
+        );
+        assert_eq!(
+            get_mime_type(Path::new("letter.dotx")),
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.template"
+        );
+        assert_eq!(
+            get_mime_type(Path::new("show.ppsx")),
+            "application/vnd.openxmlformats-officedocument.presentationml.slideshow"
+        );
+        assert_eq!(get_mime_type(Path::new("old.pps")), "application/vnd.ms-powerpoint");
+    }
+
     #[test]
     fn test_get_mime_type_unknown() {
         assert_eq!(
@@ -256,8 +961,7 @@ mod tests {
 
     #[test]
     fn test_unsupported_extension() {
-        assert!(!is_supported_extension("pdf"));
-        assert!(!is_supported_extension("jpg"));
+        assert!(!is_supported_extension("svg"));
         assert!(!is_supported_extension("zip"));
         assert!(!is_supported_extension("exe"));
     }
@@ -265,13 +969,14 @@ mod tests {
     #[test]
     fn test_supported_extensions_list() {
         let exts = supported_extensions();
-        assert_eq!(exts.len(), 13);
+        assert_eq!(exts.len(), 29);
         assert!(exts.contains(&"doc"));
         assert!(exts.contains(&"docx"));
         assert!(exts.contains(&"xlsx"));
         assert!(exts.contains(&"pptx"));
         assert!(exts.contains(&"csv"));
         assert!(exts.contains(&"odp"));
+        assert!(exts.contains(&"pdf"));
     }
 
     #[test]
@@ -298,4 +1003,383 @@ mod tests {
 
         std::fs::remove_file(&path).unwrap();
     }
+
+    /// Write `entries` to a zip file in the temp dir
+    fn write_zip(name: &str, entries: &[(&str, &str)]) -> std::path::PathBuf {
+        use std::io::Write;
+        let path = std::env::temp_dir().join(name);
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        for (entry, content) in entries {
+            zip.start_file(*entry, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    /// Write a compound file holding one stream called `stream`
+    fn write_compound_file(name: &str, stream: &str, content: &[u8]) -> std::path::PathBuf {
+        use std::io::Write;
+        let path = std::env::temp_dir().join(name);
+        let mut file = cfb::create(&path).unwrap();
+        file.create_stream(stream).unwrap().write_all(content).unwrap();
+        file.flush().unwrap();
+        path
+    }
+
+    fn sniff(path: &Path) -> Option<(&'static str, bool)> {
+        sniff_format(path)
+            .unwrap()
+            .map(|d| (d.format.extension, d.extension_mismatch))
+    }
+
+    const XLSM_CONTENT_TYPES: &str = concat!(
+        r#"<Types><Override PartName="/xl/workbook.xml" "#,
+        r#"ContentType="application/vnd.ms-excel.sheet.macroEnabled.main+xml"/></Types>"#
+    );
+
+    #[test]
+    fn test_sniff_ooxml_by_main_part() {
+        let path = write_zip(
+            "gopener_test_sniff.xlsm",
+            &[("[Content_Types].xml", XLSM_CONTENT_TYPES)],
+        );
+        assert_eq!(sniff(&path), Some(("xlsm", false)));
+
+        let renamed = std::env::temp_dir().join("gopener_test_sniff_renamed.xlsx");
+        std::fs::rename(&path, &renamed).unwrap();
+        assert_eq!(sniff(&renamed), Some(("xlsm", true)));
+        assert_eq!(
+            sniff_format(&renamed).unwrap().unwrap().warning(&renamed),
+            Some(
+                "gopener_test_sniff_renamed.xlsx is really a Microsoft Excel file, \
+                 so it was uploaded as .xlsm"
+                    .to_string()
+            )
+        );
+
+        std::fs::remove_file(&renamed).unwrap();
+    }
+
+    #[test]
+    fn test_sniff_opendocument_by_mimetype_entry() {
+        let path = write_zip(
+            "gopener_test_sniff.odp",
+            &[("mimetype", "application/vnd.oasis.opendocument.presentation")],
+        );
+        assert_eq!(sniff(&path), Some(("odp", false)));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_sniff_rejects_plain_zip() {
+        let path = write_zip("gopener_test_sniff_zip.docx", &[("notes.txt", "hello")]);
+        assert_eq!(sniff(&path), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_sniff_compound_file_keeps_matching_extension() {
+        let template = write_compound_file("gopener_test_sniff.xlt", "Workbook", &[]);
+        assert_eq!(sniff(&template), Some(("xlt", false)));
+        std::fs::remove_file(&template).unwrap();
+
+        let mislabeled =
+            write_compound_file("gopener_test_sniff_ppt.doc", "PowerPoint Document", &[]);
+        assert_eq!(sniff(&mislabeled), Some(("ppt", true)));
+        std::fs::remove_file(&mislabeled).unwrap();
+    }
+
+    #[test]
+    fn test_sniff_rtf_and_text() {
+        let path = std::env::temp_dir().join("gopener_test_sniff_rtf.doc");
+        std::fs::write(&path, "{\\rtf1\\ansi Hello}").unwrap();
+        assert_eq!(sniff(&path), Some(("rtf", true)));
+
+        std::fs::write(&path, "name,amount\r\nAcme,12\r\n").unwrap();
+        assert_eq!(sniff(&path), Some(("txt", true)));
+        std::fs::remove_file(&path).unwrap();
+
+        let csv = std::env::temp_dir().join("gopener_test_sniff.csv");
+        std::fs::write(&csv, "\u{feff}name,amount\nAcme,12\n").unwrap();
+        assert_eq!(sniff(&csv), Some(("csv", false)));
+        std::fs::remove_file(&csv).unwrap();
+    }
+
+    /// `text` as UTF-16 with a byte order mark
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        let mut bytes = if big_endian {
+            vec![0xFE, 0xFF]
+        } else {
+            vec![0xFF, 0xFE]
+        };
+        for unit in text.encode_utf16() {
+            if big_endian {
+                bytes.extend(unit.to_be_bytes());
+            } else {
+                bytes.extend(unit.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_sniff_utf16_text() {
+        let csv = std::env::temp_dir().join("gopener_test_sniff_utf16.csv");
+        std::fs::write(&csv, utf16("name,amount\r\nZürich,12\r\n", false)).unwrap();
+        assert_eq!(sniff(&csv), Some(("csv", false)));
+        std::fs::remove_file(&csv).unwrap();
+
+        let txt = std::env::temp_dir().join("gopener_test_sniff_utf16_be.doc");
+        std::fs::write(&txt, utf16("Meeting notes \u{1F4DD}\n", true)).unwrap();
+        assert_eq!(sniff(&txt), Some(("txt", true)));
+
+        std::fs::write(&txt, utf16("<html><body></body></html>", true)).unwrap();
+        assert_eq!(sniff(&txt), None);
+
+        let mut unpaired = utf16("notes", false);
+        unpaired.extend([0x00, 0xDC, b'a', 0x00]);
+        std::fs::write(&txt, unpaired).unwrap();
+        assert_eq!(sniff(&txt), None);
+        std::fs::remove_file(&txt).unwrap();
+    }
+
+    #[test]
+    fn test_sniff_rejects_html_and_binary() {
+        let path = std::env::temp_dir().join("gopener_test_sniff_html.xls");
+        std::fs::write(&path, "  <html><body><table></table></body></html>").unwrap();
+        assert_eq!(sniff(&path), None);
+
+        std::fs::write(&path, [0x7F, b'E', b'L', b'F', 0x02, 0x01, 0x00]).unwrap();
+        assert_eq!(sniff(&path), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_sniff_scans() {
+        let path = std::env::temp_dir().join("gopener_test_sniff_scan.jpeg");
+        std::fs::write(&path, b"%PDF-1.7\n").unwrap();
+        assert_eq!(sniff(&path), Some(("pdf", true)));
+
+        std::fs::write(&path, [0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
+        assert_eq!(sniff(&path), Some(("jpeg", false)));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_ooxml_is_an_encrypted_package() {
+        let path = write_compound_file("gopener_test_encrypted.docx", "EncryptedPackage", &[0; 8]);
+        assert!(is_encrypted(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_word_sets_fib_flag() {
+        let mut fib = [0u8; 32];
+        fib[..2].copy_from_slice(&0xA5ECu16.to_le_bytes());
+        let plain = write_compound_file("gopener_test_plain.doc", "WordDocument", &fib);
+        assert!(!is_encrypted(&plain).unwrap());
+        std::fs::remove_file(&plain).unwrap();
+
+        fib[10..12].copy_from_slice(&0x0100u16.to_le_bytes());
+        let encrypted = write_compound_file("gopener_test_encrypted.doc", "WordDocument", &fib);
+        assert!(is_encrypted(&encrypted).unwrap());
+        std::fs::remove_file(&encrypted).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_workbook_has_filepass_record() {
+        let bof = [0x09, 0x08, 0x04, 0x00, 0x00, 0x06, 0x05, 0x00];
+        let codepage = [0x42, 0x00, 0x02, 0x00, 0xE4, 0x04];
+        let eof = [0x0A, 0x00, 0x00, 0x00];
+        let filepass = [0x2F, 0x00, 0x02, 0x00, 0x01, 0x00];
+
+        let plain = [&bof[..], &codepage, &eof, &filepass].concat();
+        let path = write_compound_file("gopener_test_plain.xls", "Workbook", &plain);
+        assert!(!is_encrypted(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let encrypted = [&bof[..], &filepass, &codepage, &eof].concat();
+        let path = write_compound_file("gopener_test_encrypted.xls", "Workbook", &encrypted);
+        assert!(is_encrypted(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_presentation_has_encrypted_token() {
+        let mut atom = [0u8; 20];
+        atom[12..16].copy_from_slice(&0xF3D1_C4DFu32.to_le_bytes());
+        let path = write_compound_file("gopener_test_encrypted.ppt", "CurrentUser", &atom);
+        assert!(is_encrypted(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_opendocument_lists_encryption_data() {
+        let manifest = concat!(
+            r#"<manifest:file-entry manifest:full-path="content.xml">"#,
+            r#"<manifest:encryption-data/></manifest:file-entry>"#
+        );
+        let path = write_zip(
+            "gopener_test_encrypted.odt",
+            &[
+                ("mimetype", "application/vnd.oasis.opendocument.text"),
+                ("META-INF/manifest.xml", manifest),
+            ],
+        );
+        assert!(is_encrypted(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let path = write_zip(
+            "gopener_test_plain.odt",
+            &[
+                ("mimetype", "application/vnd.oasis.opendocument.text"),
+                ("META-INF/manifest.xml", "<manifest:manifest/>"),
+            ],
+        );
+        assert!(!is_encrypted(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn spreadsheet_with_dimension(name: &str, range: &str) -> std::path::PathBuf {
+        let sheet = format!(
+            r#"<worksheet><dimension ref="{}"/><sheetData><row r="1"/></sheetData></worksheet>"#,
+            range
+        );
+        write_zip(
+            name,
+            &[
+                ("[Content_Types].xml", "<Types/>"),
+                ("xl/worksheets/sheet1.xml", &sheet),
+                ("xl/worksheets/sheet2.xml", r#"<worksheet><dimension ref="A1"/></worksheet>"#),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_preflight_counts_sheet_dimensions() {
+        let xlsx = formats::by_extension("xlsx").unwrap();
+
+        let over = spreadsheet_with_dimension("gopener_test_cells_over.xlsx", "A1:D3000000");
+        assert_eq!(
+            preflight(&over, xlsx).unwrap(),
+            Preflight::OverLimit(
+                "gopener_test_cells_over.xlsx has 12,000,001 cells, more than the \
+                 10,000,000 cells Google Sheets can convert"
+                    .to_string()
+            )
+        );
+        std::fs::remove_file(&over).unwrap();
+
+        let near = spreadsheet_with_dimension("gopener_test_cells_near.xlsx", "B2:K900001");
+        assert!(matches!(
+            preflight(&near, xlsx).unwrap(),
+            Preflight::NearLimit(_)
+        ));
+        std::fs::remove_file(&near).unwrap();
+
+        let fits = spreadsheet_with_dimension("gopener_test_cells_fits.xlsx", "A1:C20");
+        assert_eq!(preflight(&fits, xlsx).unwrap(), Preflight::Fits);
+        std::fs::remove_file(&fits).unwrap();
+    }
+
+    #[test]
+    fn test_preflight_survives_huge_sheet_dimensions() {
+        let dimension = |range: &str| {
+            let sheet = format!(r#"<worksheet><dimension ref="{}"/></worksheet>"#, range);
+            sheet_dimension(sheet.as_bytes())
+        };
+        // The row count is u64::MAX, so the cells don't fit in a u64
+        assert_eq!(dimension("A0:XFD18446744073709551615"), Some(u64::MAX));
+        // Too many column letters for a u64
+        assert_eq!(dimension("A1:ZZZZZZZZZZZZZZZ1"), None);
+        assert_eq!(dimension("A1:B99999999999999999999"), None);
+
+        let xlsx = formats::by_extension("xlsx").unwrap();
+        let path = spreadsheet_with_dimension(
+            "gopener_test_cells_huge.xlsx",
+            "A1:XFD18446744073709551615",
+        );
+        assert!(matches!(
+            preflight(&path, xlsx).unwrap(),
+            Preflight::OverLimit(_)
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_opendocument_cells_ignore_formatted_padding() {
+        let content = concat!(
+            "<office:body><table:table>",
+            r#"<table:table-row><table:table-cell office:value-type="string"><text:p>a</text:p>"#,
+            r#"</table:table-cell><table:table-cell table:number-columns-repeated="2"/>"#,
+            r#"<table:table-cell office:value-type="float" office:value="1"/></table:table-row>"#,
+            r#"<table:table-row table:number-rows-repeated="2"><table:table-cell/>"#,
+            "</table:table-row>",
+            r#"<table:table-row><table:table-cell><text:p>b</text:p></table:table-cell>"#,
+            r#"</table:table-row><table:table-row table:number-rows-repeated="1048570">"#,
+            r#"<table:table-cell table:number-columns-repeated="1024"/></table:table-row>"#,
+            "</table:table></office:body>"
+        );
+        assert_eq!(opendocument_cells(content.as_bytes()).unwrap(), 4 * 4);
+    }
+
+    #[test]
+    fn test_preflight_counts_document_text() {
+        let document = concat!(
+            r#"<w:document><w:body><w:p><w:r><w:t xml:space="preserve">Fish &amp; </w:t>"#,
+            "<w:tab/><w:t>chips</w:t></w:r><w:r><w:t/></w:r></w:p>",
+            "<w:p><w:r><w:t>caf\u{e9}</w:t></w:r></w:p></w:body></w:document>"
+        );
+        let path = write_zip(
+            "gopener_test_text.docx",
+            &[("word/document.xml", document)],
+        );
+        let docx = formats::by_extension("docx").unwrap();
+
+        assert_eq!(count_characters(&path, docx), Some(16));
+        assert_eq!(preflight(&path, docx).unwrap(), Preflight::Fits);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_preflight_checks_file_size() {
+        let path = std::env::temp_dir().join("gopener_test_size.pdf");
+        std::fs::write(&path, vec![0u8; 3 * 1024 * 1024]).unwrap();
+
+        assert_eq!(
+            preflight(&path, formats::by_extension("pdf").unwrap()).unwrap(),
+            Preflight::NearLimit(
+                "gopener_test_size.pdf has 3.0 MB of data, more than the \
+                 2.0 MB of data Google Docs converts reliably"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            preflight(&path, formats::by_extension("pptx").unwrap()).unwrap(),
+            Preflight::Fits
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_with_separators() {
+        assert_eq!(with_separators(0), "0");
+        assert_eq!(with_separators(999), "999");
+        assert_eq!(with_separators(1_020_000), "1,020,000");
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let path = std::env::temp_dir().join("gopener_test_hash.txt");
+        std::fs::write(&path, "abc").unwrap();
+
+        assert_eq!(
+            content_hash(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,285 @@
+//! The one list of file formats Gopener uploads.
+//!
+//! Detection, MIME types, the Linux desktop entry and the frontend's file
+//! picker all read from `FORMATS`; `tauri.conf.json` is checked against it in
+//! the tests below.
+
+use crate::utils::file::GoogleFileType;
+use serde::Serialize;
+use std::path::Path;
+
+const MIB: u64 = 1024 * 1024;
+
+/// What Drive accepts when converting to a Google format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ConversionLimits {
+    /// Largest file Drive converts, in bytes
+    pub max_file_size: u64,
+    /// Most characters a converted document can hold
+    pub max_characters: Option<u64>,
+    /// Most cells a converted spreadsheet can hold
+    pub max_cells: Option<u64>,
+    /// Size past which Drive still converts but does a worse job, in bytes
+    pub recommended_file_size: Option<u64>,
+}
+
+const DOCUMENT_LIMITS: ConversionLimits = ConversionLimits {
+    max_file_size: 50 * MIB,
+    max_characters: Some(1_020_000),
+    max_cells: None,
+    recommended_file_size: None,
+};
+
+const SPREADSHEET_LIMITS: ConversionLimits = ConversionLimits {
+    max_file_size: 100 * MIB,
+    max_characters: None,
+    max_cells: Some(10_000_000),
+    recommended_file_size: None,
+};
+
+const PRESENTATION_LIMITS: ConversionLimits = ConversionLimits {
+    max_file_size: 100 * MIB,
+    max_characters: None,
+    max_cells: None,
+    recommended_file_size: None,
+};
+
+/// Scans become documents, but Drive only reads text reliably from ones up to 2 MB
+const SCAN_LIMITS: ConversionLimits = ConversionLimits {
+    recommended_file_size: Some(2 * MIB),
+    ..DOCUMENT_LIMITS
+};
+
+/// A file format Gopener can upload and convert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Format {
+    /// Lowercase extension without the dot
+    pub extension: &'static str,
+    /// MIME type of the file as uploaded
+    pub mime_type: &'static str,
+    /// Google format it converts to
+    pub target: GoogleFileType,
+    /// Name of the format itself, reported when the file is kept unconverted
+    pub name: &'static str,
+    /// Converted by recognizing text, as for PDFs and images
+    pub ocr: bool,
+    pub limits: ConversionLimits,
+}
+
+const fn document(extension: &'static str, mime_type: &'static str, name: &'static str) -> Format {
+    Format {
+        extension,
+        mime_type,
+        target: GoogleFileType::Document,
+        name,
+        ocr: false,
+        limits: DOCUMENT_LIMITS,
+    }
+}
+
+const fn scan(extension: &'static str, mime_type: &'static str, name: &'static str) -> Format {
+    Format {
+        extension,
+        mime_type,
+        target: GoogleFileType::Document,
+        name,
+        ocr: true,
+        limits: SCAN_LIMITS,
+    }
+}
+
+const fn spreadsheet(
+    extension: &'static str,
+    mime_type: &'static str,
+    name: &'static str,
+) -> Format {
+    Format {
+        extension,
+        mime_type,
+        target: GoogleFileType::Spreadsheet,
+        name,
+        ocr: false,
+        limits: SPREADSHEET_LIMITS,
+    }
+}
+
+const fn presentation(
+    extension: &'static str,
+    mime_type: &'static str,
+    name: &'static str,
+) -> Format {
+    Format {
+        extension,
+        mime_type,
+        target: GoogleFileType::Presentation,
+        name,
+        ocr: false,
+        limits: PRESENTATION_LIMITS,
+    }
+}
+
+const WORD: &str = "Microsoft Word";
+const EXCEL: &str = "Microsoft Excel";
+const POWERPOINT: &str = "Microsoft PowerPoint";
+
+/// Every supported format, grouped by Google target
+pub static FORMATS: &[Format] = &[
+    // Google Docs
+    document("doc", "application/msword", WORD),
+    document(
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        WORD,
+    ),
+    document(
+        "docm",
+        "application/vnd.ms-word.document.macroEnabled.12",
+        WORD,
+    ),
+    document(
+        "dotx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.template",
+        WORD,
+    ),
+    document("dot", "application/msword", WORD),
+    document(
+        "odt",
+        "application/vnd.oasis.opendocument.text",
+        "OpenDocument Text",
+    ),
+    document("rtf", "application/rtf", "Rich Text"),
+    document("txt", "text/plain", "Plain Text"),
+    // Google Docs, by text recognition
+    scan("pdf", "application/pdf", "PDF"),
+    scan("png", "image/png", "PNG image"),
+    scan("jpg", "image/jpeg", "JPEG image"),
+    scan("jpeg", "image/jpeg", "JPEG image"),
+    scan("gif", "image/gif", "GIF image"),
+    // Google Sheets
+    spreadsheet("xls", "application/vnd.ms-excel", EXCEL),
+    spreadsheet(
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        EXCEL,
+    ),
+    spreadsheet(
+        "xlsm",
+        "application/vnd.ms-excel.sheet.macroEnabled.12",
+        EXCEL,
+    ),
+    spreadsheet(
+        "xlsb",
+        "application/vnd.ms-excel.sheet.binary.macroEnabled.12",
+        EXCEL,
+    ),
+    spreadsheet(
+        "xltx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.template",
+        EXCEL,
+    ),
+    spreadsheet("xlt", "application/vnd.ms-excel", EXCEL),
+    spreadsheet(
+        "ods",
+        "application/vnd.oasis.opendocument.spreadsheet",
+        "OpenDocument Spreadsheet",
+    ),
+    spreadsheet("csv", "text/csv", "CSV"),
+    spreadsheet("tsv", "text/tab-separated-values", "TSV"),
+    // Google Slides
+    presentation("ppt", "application/vnd.ms-powerpoint", POWERPOINT),
+    presentation(
+        "pptx",
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        POWERPOINT,
+    ),
+    presentation(
+        "pptm",
+        "application/vnd.ms-powerpoint.presentation.macroEnabled.12",
+        POWERPOINT,
+    ),
+    presentation(
+        "potx",
+        "application/vnd.openxmlformats-officedocument.presentationml.template",
+        POWERPOINT,
+    ),
+    presentation("pps", "application/vnd.ms-powerpoint", POWERPOINT),
+    presentation(
+        "ppsx",
+        "application/vnd.openxmlformats-officedocument.presentationml.slideshow",
+        POWERPOINT,
+    ),
+    presentation(
+        "odp",
+        "application/vnd.oasis.opendocument.presentation",
+        "OpenDocument Presentation",
+    ),
+];
+
+/// Format for an extension, with or without the dot, in any case
+pub fn by_extension(extension: &str) -> Option<&'static Format> {
+    let extension = extension.trim_start_matches('.');
+    FORMATS
+        .iter()
+        .find(|format| format.extension.eq_ignore_ascii_case(extension))
+}
+
+/// Format of a file, judged by its extension
+pub fn by_path(path: &Path) -> Option<&'static Format> {
+    by_extension(path.extension()?.to_str()?)
+}
+
+/// Distinct source MIME types, in registry order
+pub fn mime_types() -> Vec<&'static str> {
+    let mut mime_types: Vec<&'static str> = Vec::new();
+    for format in FORMATS {
+        if !mime_types.contains(&format.mime_type) {
+            mime_types.push(format.mime_type);
+        }
+    }
+    mime_types
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_extensions_are_unique_and_lowercase() {
+        let mut seen = BTreeSet::new();
+        for format in FORMATS {
+            assert_eq!(format.extension, format.extension.to_lowercase());
+            assert!(seen.insert(format.extension), "{}", format.extension);
+        }
+    }
+
+    #[test]
+    fn test_lookup_ignores_case_and_dot() {
+        assert_eq!(
+            by_extension(".XLSM").unwrap().target,
+            GoogleFileType::Spreadsheet
+        );
+        assert_eq!(
+            by_path(Path::new("/tmp/Deck.ppsx")).unwrap().name,
+            POWERPOINT
+        );
+        assert!(by_path(Path::new("/tmp/README")).is_none());
+        assert!(by_extension("svg").is_none());
+    }
+
+    #[test]
+    fn test_bundle_associations_match_registry() {
+        let config: serde_json::Value =
+            serde_json::from_str(include_str!("../../tauri.conf.json")).unwrap();
+        let associated: BTreeSet<&str> = config["bundle"]["fileAssociations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|association| association["ext"].as_array().unwrap())
+            .map(|ext| ext.as_str().unwrap())
+            .collect();
+        let registered: BTreeSet<&str> = FORMATS.iter().map(|f| f.extension).collect();
+
+        assert_eq!(associated, registered);
+    }
+}
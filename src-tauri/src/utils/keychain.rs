@@ -41,14 +41,12 @@ pub fn delete(key: &str) -> Result<()> {
 }
 
 /// Store a JSON-serializable value
-#[allow(dead_code)]
 pub fn store_json<T: serde::Serialize>(key: &str, value: &T) -> Result<()> {
     let json = serde_json::to_string(value)?;
     store(key, &json)
 }
 
 /// Retrieve and deserialize a JSON value
-#[allow(dead_code)]
 pub fn retrieve_json<T: serde::de::DeserializeOwned>(key: &str) -> Result<Option<T>> {
     match retrieve(key)? {
         Some(json) => {
@@ -66,7 +64,7 @@ pub mod keys {
     pub const TOKEN_EXPIRY: &str = "token_expiry";
     pub const CUSTOM_CLIENT_ID: &str = "custom_client_id";
     pub const CUSTOM_CLIENT_SECRET: &str = "custom_client_secret";
-    pub const PKCE_VERIFIER: &str = "pkce_verifier";
+    pub const PENDING_AUTHORIZATION: &str = "pending_authorization";
 }
 
 #[cfg(test)]
@@ -81,7 +79,7 @@ mod tests {
             keys::TOKEN_EXPIRY,
             keys::CUSTOM_CLIENT_ID,
             keys::CUSTOM_CLIENT_SECRET,
-            keys::PKCE_VERIFIER,
+            keys::PENDING_AUTHORIZATION,
         ];
 
         let mut unique = std::collections::HashSet::new();
@@ -98,7 +96,7 @@ mod tests {
         assert!(!keys::TOKEN_EXPIRY.is_empty());
         assert!(!keys::CUSTOM_CLIENT_ID.is_empty());
         assert!(!keys::CUSTOM_CLIENT_SECRET.is_empty());
-        assert!(!keys::PKCE_VERIFIER.is_empty());
+        assert!(!keys::PENDING_AUTHORIZATION.is_empty());
     }
 
     #[test]
@@ -0,0 +1,299 @@
+//! Short-lived loopback HTTP listener that receives the OAuth redirect.
+//!
+//! Only the request line of each incoming request is inspected; the browser is
+//! answered with a small static page and the connection is closed.
+
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+/// Time one connection gets to send its request before it is dropped
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Query parameters Google appends to the redirect URI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Bind a listener for the given redirect URI.
+///
+/// Uses the port from the URI when it is free and falls back to a port chosen by
+/// the OS otherwise. Returns the listener together with the redirect URI that
+/// actually matches it.
+pub async fn bind(redirect_uri: &str) -> std::io::Result<(TcpListener, String)> {
+    let mut parsed = url::Url::parse(redirect_uri)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let port = parsed.port_or_known_default().unwrap_or(0);
+
+    let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+        Ok(listener) => listener,
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+            TcpListener::bind(("127.0.0.1", 0)).await?
+        }
+        Err(e) => return Err(e),
+    };
+
+    let actual_port = listener.local_addr()?.port();
+    if actual_port == port {
+        return Ok((listener, redirect_uri.to_string()));
+    }
+
+    let _ = parsed.set_port(Some(actual_port));
+    let mut actual_uri = parsed.to_string();
+    // Url always renders a bare origin with a trailing slash; keep the configured shape
+    if !redirect_uri.ends_with('/') && parsed.path() == "/" {
+        actual_uri.pop();
+    }
+
+    Ok((listener, actual_uri))
+}
+
+/// Accept connections until one carries an OAuth `code` or `error`.
+///
+/// Unrelated requests (favicon lookups, probes) get a 404 and are ignored.
+/// Each connection is served on its own task, so a browser preconnect that
+/// never sends anything can't hold up the real redirect.
+pub async fn wait_for_callback(listener: &TcpListener) -> std::io::Result<CallbackParams> {
+    let (found, mut callbacks) = mpsc::channel(1);
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let stream = match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::ConnectionAborted | std::io::ErrorKind::ConnectionReset
+                    ) => continue,
+                    Err(e) => return Err(e),
+                };
+                let found = found.clone();
+                tokio::spawn(async move {
+                    // A connection that stalls or fails is not the redirect; drop it
+                    let handled = tokio::time::timeout(CONNECTION_TIMEOUT, handle_connection(stream));
+                    if let Ok(Ok(Some(params))) = handled.await {
+                        let _ = found.send(params).await;
+                    }
+                });
+            }
+            Some(params) = callbacks.recv() => return Ok(params),
+        }
+    }
+}
+
+async fn handle_connection(stream: TcpStream) -> std::io::Result<Option<CallbackParams>> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    // The client hung up before finishing the request line
+    if !request_line.ends_with('\n') {
+        return Ok(None);
+    }
+
+    // Drain the remaining headers so the browser does not see a reset connection
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let mut stream = reader.into_inner();
+    let params = parse_request_line(&request_line);
+
+    let response = match &params {
+        Some(params) => {
+            let body = match &params.error {
+                Some(error) => render_page(
+                    "Sign-in failed",
+                    &format!("Google returned an error: {}", error),
+                ),
+                None => render_page(
+                    "Signed in to Gopener",
+                    "You can close this tab and return to the app.",
+                ),
+            };
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        None => {
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        }
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    let _ = stream.shutdown().await;
+
+    Ok(params)
+}
+
+/// Extract callback parameters from an HTTP request line such as
+/// `GET /?code=abc&state=xyz HTTP/1.1`
+pub fn parse_request_line(request_line: &str) -> Option<CallbackParams> {
+    let mut parts = request_line.split_whitespace();
+    if parts.next()? != "GET" {
+        return None;
+    }
+    let target = parts.next()?;
+    let url = url::Url::parse(&format!("http://localhost{}", target)).ok()?;
+
+    let mut params = CallbackParams {
+        code: None,
+        state: None,
+        error: None,
+    };
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "code" => params.code = Some(value.to_string()),
+            "state" => params.state = Some(value.to_string()),
+            "error" => params.error = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    if params.code.is_none() && params.error.is_none() {
+        return None;
+    }
+    Some(params)
+}
+
+fn render_page(title: &str, message: &str) -> String {
+    format!(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><title>{title}</title></head>\
+         <body style=\"font-family: sans-serif; text-align: center; padding-top: 4em;\">\
+         <h1>{title}</h1><p>{message}</p></body></html>",
+        title = escape_html(title),
+        message = escape_html(message)
+    )
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_parse_request_line_with_code() {
+        let params = parse_request_line("GET /?code=4%2F0abc&state=xyz HTTP/1.1").unwrap();
+        assert_eq!(params.code, Some("4/0abc".to_string()));
+        assert_eq!(params.state, Some("xyz".to_string()));
+        assert!(params.error.is_none());
+    }
+
+    #[test]
+    fn test_parse_request_line_with_error() {
+        let params = parse_request_line("GET /?error=access_denied HTTP/1.1").unwrap();
+        assert_eq!(params.error, Some("access_denied".to_string()));
+        assert!(params.code.is_none());
+    }
+
+    #[test]
+    fn test_parse_request_line_ignores_unrelated_requests() {
+        assert!(parse_request_line("GET /favicon.ico HTTP/1.1").is_none());
+        assert!(parse_request_line("POST /?code=abc HTTP/1.1").is_none());
+        assert!(parse_request_line("").is_none());
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<script>alert('x')</script>"),
+            "&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bind_falls_back_when_port_is_taken() {
+        let taken = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let uri = format!("http://localhost:{}", port);
+
+        let (listener, actual_uri) = bind(&uri).await.unwrap();
+        let actual_port = listener.local_addr().unwrap().port();
+
+        assert_ne!(actual_port, port);
+        assert_eq!(actual_uri, format!("http://localhost:{}", actual_port));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_callback_skips_unrelated_requests() {
+        let (listener, uri) = bind("http://localhost:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let browser = tokio::spawn(async move {
+            for target in ["/favicon.ico", "/?code=abc&state=s1"] {
+                let mut stream = TcpStream::connect(addr).await.unwrap();
+                stream
+                    .write_all(
+                        format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+                if target.contains("code") {
+                    assert!(response.starts_with("HTTP/1.1 200"));
+                    assert!(response.contains("You can close this tab"));
+                } else {
+                    assert!(response.starts_with("HTTP/1.1 404"));
+                }
+            }
+        });
+
+        let params = wait_for_callback(&listener).await.unwrap();
+        browser.await.unwrap();
+
+        assert!(uri.starts_with("http://localhost:"));
+        assert_eq!(params.code, Some("abc".to_string()));
+        assert_eq!(params.state, Some("s1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_callback_survives_silent_and_broken_connections() {
+        let (listener, _) = bind("http://localhost:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let browser = tokio::spawn(async move {
+            // A preconnect that never sends a request stays open throughout
+            let silent = TcpStream::connect(addr).await.unwrap();
+            // A client that hangs up mid-request
+            let mut broken = TcpStream::connect(addr).await.unwrap();
+            broken.write_all(b"GET /?code=").await.unwrap();
+            drop(broken);
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream
+                .write_all(b"GET /?code=xyz&state=s2 HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            drop(silent);
+            response
+        });
+
+        let params = tokio::time::timeout(Duration::from_secs(5), wait_for_callback(&listener))
+            .await
+            .expect("callback was held up by another connection")
+            .unwrap();
+
+        assert_eq!(params.code, Some("xyz".to_string()));
+        assert!(browser.await.unwrap().starts_with("HTTP/1.1 200"));
+    }
+}
@@ -1,2 +1,8 @@
 pub mod file;
+pub mod formats;
 pub mod keychain;
+pub mod loopback;
+pub mod progress;
+pub mod retry;
+#[cfg(test)]
+pub mod test_server;
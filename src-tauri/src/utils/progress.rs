@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Span of recent samples used to estimate throughput
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Tracks transfer progress, estimates throughput and throttles update events
+#[derive(Debug)]
+pub struct ProgressTracker {
+    total: u64,
+    bytes: u64,
+    samples: VecDeque<(Instant, u64)>,
+    min_interval: Duration,
+    last_emit: Option<Instant>,
+    last_emitted_bytes: u64,
+}
+
+impl ProgressTracker {
+    pub fn new(total: u64, min_interval: Duration) -> Self {
+        Self {
+            total,
+            bytes: 0,
+            samples: VecDeque::new(),
+            min_interval,
+            last_emit: None,
+            last_emitted_bytes: 0,
+        }
+    }
+
+    /// Record the number of bytes sent so far.
+    ///
+    /// Returns `true` when an update should be emitted: the first sample, once
+    /// `min_interval` has passed since the previous update, and on completion.
+    pub fn record(&mut self, bytes: u64, now: Instant) -> bool {
+        self.bytes = bytes.min(self.total);
+        self.samples.push_back((now, self.bytes));
+
+        // Keep one sample older than the window so the rate always spans it
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].0) >= RATE_WINDOW {
+            self.samples.pop_front();
+        }
+
+        let finished = self.bytes >= self.total && self.last_emitted_bytes < self.total;
+        let due = match self.last_emit {
+            None => true,
+            Some(last) => now.duration_since(last) >= self.min_interval,
+        };
+
+        if due || finished {
+            self.last_emit = Some(now);
+            self.last_emitted_bytes = self.bytes;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn percentage(&self) -> f64 {
+        if self.total > 0 {
+            (self.bytes as f64 / self.total as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Recent throughput in bytes per second
+    pub fn bytes_per_second(&self) -> f64 {
+        let (Some(first), Some(last)) = (self.samples.front(), self.samples.back()) else {
+            return 0.0;
+        };
+        let elapsed = last.0.duration_since(first.0).as_secs_f64();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        last.1.saturating_sub(first.1) as f64 / elapsed
+    }
+
+    /// Estimated seconds until the transfer completes, if there is a measurable rate
+    pub fn eta_seconds(&self) -> Option<f64> {
+        let rate = self.bytes_per_second();
+        if rate <= 0.0 {
+            return None;
+        }
+        Some(self.total.saturating_sub(self.bytes) as f64 / rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(200);
+
+    #[test]
+    fn test_first_sample_is_emitted() {
+        let mut tracker = ProgressTracker::new(1000, INTERVAL);
+        assert!(tracker.record(0, Instant::now()));
+    }
+
+    #[test]
+    fn test_updates_are_throttled() {
+        let start = Instant::now();
+        let mut tracker = ProgressTracker::new(1000, INTERVAL);
+
+        assert!(tracker.record(10, start));
+        assert!(!tracker.record(20, start + Duration::from_millis(50)));
+        assert!(!tracker.record(30, start + Duration::from_millis(150)));
+        assert!(tracker.record(40, start + Duration::from_millis(200)));
+        assert_eq!(tracker.bytes(), 40);
+    }
+
+    #[test]
+    fn test_completion_is_always_emitted_once() {
+        let start = Instant::now();
+        let mut tracker = ProgressTracker::new(100, INTERVAL);
+
+        assert!(tracker.record(10, start));
+        assert!(tracker.record(100, start + Duration::from_millis(1)));
+        assert!(!tracker.record(100, start + Duration::from_millis(2)));
+        assert_eq!(tracker.percentage(), 100.0);
+    }
+
+    #[test]
+    fn test_rate_and_eta() {
+        let start = Instant::now();
+        let mut tracker = ProgressTracker::new(10_000, INTERVAL);
+
+        tracker.record(0, start);
+        tracker.record(1_000, start + Duration::from_secs(1));
+        tracker.record(2_000, start + Duration::from_secs(2));
+
+        assert!((tracker.bytes_per_second() - 1_000.0).abs() < 1e-6);
+        assert!((tracker.eta_seconds().unwrap() - 8.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rate_uses_recent_window() {
+        let start = Instant::now();
+        let mut tracker = ProgressTracker::new(1_000_000, INTERVAL);
+
+        // Slow start, then a fast stretch longer than the window
+        tracker.record(0, start);
+        tracker.record(100, start + Duration::from_secs(10));
+        for i in 1..=10 {
+            tracker.record(100 + i * 10_000, start + Duration::from_secs(10 + i));
+        }
+
+        assert!((tracker.bytes_per_second() - 10_000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_no_rate_without_elapsed_time() {
+        let mut tracker = ProgressTracker::new(1000, INTERVAL);
+        assert_eq!(tracker.bytes_per_second(), 0.0);
+        assert!(tracker.eta_seconds().is_none());
+
+        tracker.record(500, Instant::now());
+        assert_eq!(tracker.bytes_per_second(), 0.0);
+        assert!(tracker.eta_seconds().is_none());
+    }
+
+    #[test]
+    fn test_bytes_are_clamped_to_total() {
+        let mut tracker = ProgressTracker::new(100, INTERVAL);
+        tracker.record(150, Instant::now());
+        assert_eq!(tracker.bytes(), 100);
+        assert_eq!(tracker.total(), 100);
+    }
+
+    #[test]
+    fn test_zero_total() {
+        let tracker = ProgressTracker::new(0, INTERVAL);
+        assert_eq!(tracker.percentage(), 0.0);
+    }
+}
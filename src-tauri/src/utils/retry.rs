@@ -0,0 +1,526 @@
+//! Retry policy shared by every request made to Google.
+//!
+//! Transient failures (connection errors, 429, 5xx and rate-limit 403s) are
+//! retried with exponential backoff and jitter, honouring `Retry-After` when the
+//! server sends one. Anything else is handed back to the caller untouched.
+//!
+//! Requests that must not run twice, such as creating a folder or redeeming an
+//! authorization code, use [`RetryPolicy::non_idempotent`]: they are only
+//! retried when Google can't have acted on them, which includes every
+//! rate-limit refusal.
+
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::time::{Duration, SystemTime};
+
+/// Error reasons Drive uses for 403s that succeed when retried later
+pub const RATE_LIMIT_REASONS: &[&str] = &["userRateLimitExceeded", "rateLimitExceeded"];
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each further retry
+    pub base_delay: Duration,
+    /// Upper bound for any single delay, including server-requested ones
+    pub max_delay: Duration,
+    /// Randomise each delay between half and all of its nominal length
+    pub jitter: bool,
+    /// Whether repeating the request is harmless once Google may have received it
+    pub idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(32),
+            jitter: true,
+            idempotent: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The same limits for a request that must not run twice.
+    ///
+    /// Such a request is retried only when it never got through (connection
+    /// errors) or when Google refused it outright: 429, rate-limit 403s, and
+    /// 503 with a `Retry-After`.
+    pub fn non_idempotent(&self) -> Self {
+        Self {
+            idempotent: false,
+            ..self.clone()
+        }
+    }
+
+    /// Delay before retry number `retry` (1 for the first retry)
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(requested) = retry_after {
+            return requested.min(self.max_delay);
+        }
+
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let nominal = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        if self.jitter {
+            nominal.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+        } else {
+            nominal
+        }
+    }
+
+    /// Send the request built by `build`, retrying transient failures.
+    ///
+    /// `build` is called once per attempt because a request cannot be sent twice.
+    /// Once attempts run out the last response (or error) is returned as is, so
+    /// callers report errors exactly as they would without retries.
+    pub async fn send<F>(&self, build: F) -> Result<Response, reqwest::Error>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let mut attempt = 1;
+        loop {
+            let last_attempt = attempt >= self.max_attempts;
+
+            let retry_after = match build().send().await {
+                Ok(response) if last_attempt || !may_retry(response.status()) => {
+                    return Ok(response)
+                }
+                Ok(response) if response.status() == StatusCode::FORBIDDEN => {
+                    // Only rate-limit 403s are worth retrying, which takes a look at the body
+                    let retry_after = parse_retry_after(response.headers());
+                    let (response, rate_limited) = inspect_forbidden(response).await?;
+                    if !rate_limited {
+                        return Ok(response);
+                    }
+                    retry_after
+                }
+                Ok(response) if !self.idempotent => {
+                    // Rate limits are enforced before Drive acts on a request; a
+                    // 503 only says so when it asks to be retried later
+                    let retry_after = parse_retry_after(response.headers());
+                    let refused = match response.status() {
+                        StatusCode::TOO_MANY_REQUESTS => true,
+                        StatusCode::SERVICE_UNAVAILABLE => retry_after.is_some(),
+                        _ => false,
+                    };
+                    if !refused {
+                        return Ok(response);
+                    }
+                    retry_after
+                }
+                Ok(response) => parse_retry_after(response.headers()),
+                Err(e) if !last_attempt && self.retries_error(&e) => None,
+                Err(e) => return Err(e),
+            };
+
+            tokio::time::sleep(self.delay(attempt, retry_after)).await;
+            attempt += 1;
+        }
+    }
+
+    /// A request that may have reached Google is only repeated when that is harmless
+    fn retries_error(&self, error: &reqwest::Error) -> bool {
+        if self.idempotent {
+            is_transient(error)
+        } else {
+            error.is_connect()
+        }
+    }
+}
+
+/// Whether a status can ever be retried; 403 additionally depends on the body
+fn may_retry(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::FORBIDDEN
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+}
+
+/// Whether a failed response should be retried
+pub fn is_retryable_status(status: StatusCode, body: &str) -> bool {
+    if status == StatusCode::FORBIDDEN {
+        return is_rate_limit_error(body);
+    }
+    may_retry(status)
+}
+
+/// Whether an error body carries one of Drive's rate-limit reasons
+pub fn is_rate_limit_error(body: &str) -> bool {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        return false;
+    };
+    value["error"]["errors"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e["reason"].as_str())
+        .any(|reason| RATE_LIMIT_REASONS.contains(&reason))
+}
+
+/// Read a 403 body to classify it, then rebuild the response for the caller
+async fn inspect_forbidden(response: Response) -> Result<(Response, bool), reqwest::Error> {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?;
+    let rate_limited = is_rate_limit_error(&String::from_utf8_lossy(&body));
+
+    let mut rebuilt = http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.headers_mut() = headers;
+
+    Ok((Response::from(rebuilt), rate_limited))
+}
+
+/// Connection-level failures that say nothing about the request itself
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request() || error.is_body()
+}
+
+/// Parse `Retry-After` as either delay seconds or an HTTP date
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(
+        at.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{ScriptedResponse, TestServer};
+    use reqwest::Client;
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(20),
+            jitter: false,
+            idempotent: true,
+        }
+    }
+
+    fn rate_limited() -> ScriptedResponse {
+        ScriptedResponse::json(
+            403,
+            serde_json::json!({
+                "error": {
+                    "code": 403,
+                    "message": "User Rate Limit Exceeded",
+                    "errors": [{"reason": "userRateLimitExceeded"}]
+                }
+            }),
+        )
+    }
+
+    fn forbidden() -> ScriptedResponse {
+        ScriptedResponse::json(
+            403,
+            serde_json::json!({
+                "error": {
+                    "code": 403,
+                    "message": "Insufficient permissions",
+                    "errors": [{"reason": "insufficientFilePermissions"}]
+                }
+            }),
+        )
+    }
+
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, retry_after.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_delay_grows_exponentially_up_to_max() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            jitter: false,
+            idempotent: true,
+        };
+
+        assert_eq!(policy.delay(1, None), Duration::from_millis(100));
+        assert_eq!(policy.delay(2, None), Duration::from_millis(200));
+        assert_eq!(policy.delay(3, None), Duration::from_millis(400));
+        assert_eq!(policy.delay(4, None), Duration::from_millis(500));
+        assert_eq!(policy.delay(40, None), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_delay_with_jitter_stays_in_range() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            ..RetryPolicy::default()
+        };
+
+        for _ in 0..50 {
+            let delay = policy.delay(2, None);
+            assert!(delay >= Duration::from_millis(100));
+            assert!(delay <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn test_retry_after_overrides_backoff_but_is_capped() {
+        let policy = fast_policy(3);
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_millis(7))),
+            Duration::from_millis(7)
+        );
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(60))),
+            Duration::from_millis(20)
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(
+            parse_retry_after(&headers("120")),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after(&headers("soon")), None);
+        assert_eq!(parse_retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS, ""));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE, ""));
+        assert!(is_retryable_status(StatusCode::INTERNAL_SERVER_ERROR, ""));
+        assert!(!is_retryable_status(StatusCode::BAD_REQUEST, ""));
+        assert!(!is_retryable_status(StatusCode::UNAUTHORIZED, ""));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND, ""));
+        assert!(!is_retryable_status(StatusCode::FORBIDDEN, "not json"));
+        assert!(is_retryable_status(
+            StatusCode::FORBIDDEN,
+            &rate_limited().body
+        ));
+        assert!(!is_retryable_status(
+            StatusCode::FORBIDDEN,
+            &forbidden().body
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_server_errors() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::new(503, "unavailable"),
+            ScriptedResponse::new(500, "oops"),
+            ScriptedResponse::new(200, "ok"),
+        ])
+        .await;
+        let client = Client::new();
+
+        let response = fast_policy(5)
+            .send(|| client.get(&server.url))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_send_honours_retry_after() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::new(429, "slow down").header("Retry-After", "0"),
+            ScriptedResponse::new(200, "ok"),
+        ])
+        .await;
+        let client = Client::new();
+        let policy = RetryPolicy {
+            base_delay: Duration::from_secs(30),
+            ..fast_policy(3)
+        };
+
+        let started = std::time::Instant::now();
+        let response = policy.send(|| client.get(&server.url)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_rate_limit_forbidden() {
+        let server =
+            TestServer::start(vec![rate_limited(), ScriptedResponse::new(200, "ok")]).await;
+        let client = Client::new();
+
+        let response = fast_policy(3)
+            .send(|| client.get(&server.url))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_send_does_not_retry_permission_errors() {
+        let server = TestServer::start(vec![forbidden(), ScriptedResponse::new(200, "ok")]).await;
+        let client = Client::new();
+
+        let response = fast_policy(3)
+            .send(|| client.get(&server.url))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .contains("insufficientFilePermissions"));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_does_not_retry_client_errors() {
+        let server = TestServer::start(vec![ScriptedResponse::new(400, "bad request")]).await;
+        let client = Client::new();
+
+        let response = fast_policy(3)
+            .send(|| client.get(&server.url))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_returns_last_response_when_attempts_run_out() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::new(503, "first"),
+            ScriptedResponse::new(503, "second"),
+            ScriptedResponse::new(503, "third"),
+        ])
+        .await;
+        let client = Client::new();
+
+        let response = fast_policy(3)
+            .send(|| client.get(&server.url))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.text().await.unwrap(), "third");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_non_idempotent_send_does_not_repeat_server_errors() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::new(500, "oops"),
+            ScriptedResponse::new(200, "ok"),
+        ])
+        .await;
+        let client = Client::new();
+
+        let response = fast_policy(3)
+            .non_idempotent()
+            .send(|| client.post(&server.url))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_non_idempotent_send_retries_when_told_to_come_back() {
+        let server = TestServer::start(vec![
+            ScriptedResponse::new(503, "busy"),
+            ScriptedResponse::new(429, "slow down").header("Retry-After", "0"),
+            ScriptedResponse::new(200, "ok"),
+        ])
+        .await;
+        let client = Client::new();
+
+        let policy = fast_policy(3).non_idempotent();
+        let first = policy.send(|| client.post(&server.url)).await.unwrap();
+        assert_eq!(first.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let second = policy.send(|| client.post(&server.url)).await.unwrap();
+        assert_eq!(second.status(), StatusCode::OK);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_non_idempotent_send_retries_rate_limits() {
+        let server = TestServer::start(vec![
+            rate_limited(),
+            ScriptedResponse::new(429, "slow down"),
+            ScriptedResponse::new(200, "ok"),
+        ])
+        .await;
+        let client = Client::new();
+
+        let response = fast_policy(3)
+            .non_idempotent()
+            .send(|| client.post(&server.url))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_non_idempotent_send_does_not_repeat_permission_errors() {
+        let server = TestServer::start(vec![forbidden(), ScriptedResponse::new(200, "ok")]).await;
+        let client = Client::new();
+
+        let response = fast_policy(3)
+            .non_idempotent()
+            .send(|| client.post(&server.url))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_connection_errors() {
+        // Grab a free port and close it again so nothing is listening
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let client = Client::new();
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+
+        let result = fast_policy(3)
+            .send(|| {
+                attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                client.get(&url)
+            })
+            .await;
+
+        assert!(result.unwrap_err().is_connect());
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+}
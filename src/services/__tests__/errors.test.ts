@@ -0,0 +1,30 @@
+import { describe, it, expect } from "vitest";
+import { errorCode, errorMessage, isGopenerError } from "../errors";
+
+const quotaExceeded = {
+  code: "quota_exceeded",
+  message: "Upload failed: The user's Drive storage quota has been exceeded.",
+  retryable: false,
+  reason: "storageQuotaExceeded",
+};
+
+describe("errors", () => {
+  it("recognises backend errors", () => {
+    expect(isGopenerError(quotaExceeded)).toBe(true);
+    expect(isGopenerError(new Error("boom"))).toBe(false);
+    expect(isGopenerError("boom")).toBe(false);
+    expect(isGopenerError(null)).toBe(false);
+  });
+
+  it("uses the backend message when there is one", () => {
+    expect(errorMessage(quotaExceeded)).toBe(
+      "Upload failed: The user's Drive storage quota has been exceeded."
+    );
+    expect(errorMessage(new Error("boom"))).toBe("Error: boom");
+  });
+
+  it("exposes the code of backend errors only", () => {
+    expect(errorCode(quotaExceeded)).toBe("quota_exceeded");
+    expect(errorCode(new Error("boom"))).toBeNull();
+  });
+});
@@ -0,0 +1,32 @@
+import type {
+  GoogleFileType,
+  SupportedFormat,
+} from "../tauri-commands";
+
+function formats(
+  target: GoogleFileType,
+  extensions: string[],
+  ocr = false
+): SupportedFormat[] {
+  return extensions.map((extension) => ({
+    extension,
+    mime_type: "application/octet-stream",
+    target,
+    name: extension.toUpperCase(),
+    ocr,
+    limits: {
+      max_file_size: 50 * 1024 * 1024,
+      max_characters: null,
+      max_cells: null,
+      recommended_file_size: null,
+    },
+  }));
+}
+
+/** Stand-in for what `get_supported_formats` returns */
+export const TEST_FORMATS: SupportedFormat[] = [
+  ...formats("document", ["doc", "docx", "docm", "dotx", "dot", "odt", "rtf", "txt"]),
+  ...formats("document", ["pdf", "png", "jpg", "jpeg", "gif"], true),
+  ...formats("spreadsheet", ["xls", "xlsx", "xlsm", "xlsb", "xltx", "xlt", "ods", "csv", "tsv"]),
+  ...formats("presentation", ["ppt", "pptx", "pptm", "potx", "pps", "ppsx", "odp"]),
+];
@@ -6,8 +6,8 @@ import {
   isSupportedExtension,
   getAllSupportedExtensions,
   GOOGLE_MIME_TYPES,
-  SUPPORTED_EXTENSIONS,
 } from "../google-api";
+import { TEST_FORMATS } from "./formats.fixture";
 
 describe("google-api", () => {
   describe("GOOGLE_MIME_TYPES", () => {
@@ -27,66 +27,49 @@ describe("google-api", () => {
     });
   });
 
-  describe("SUPPORTED_EXTENSIONS", () => {
-    it("has document extensions", () => {
-      expect(SUPPORTED_EXTENSIONS.document).toContain("doc");
-      expect(SUPPORTED_EXTENSIONS.document).toContain("docx");
-      expect(SUPPORTED_EXTENSIONS.document).toContain("odt");
-      expect(SUPPORTED_EXTENSIONS.document).toContain("rtf");
-      expect(SUPPORTED_EXTENSIONS.document).toContain("txt");
-    });
-
-    it("has spreadsheet extensions", () => {
-      expect(SUPPORTED_EXTENSIONS.spreadsheet).toContain("xls");
-      expect(SUPPORTED_EXTENSIONS.spreadsheet).toContain("xlsx");
-      expect(SUPPORTED_EXTENSIONS.spreadsheet).toContain("csv");
-    });
-
-    it("has presentation extensions", () => {
-      expect(SUPPORTED_EXTENSIONS.presentation).toContain("ppt");
-      expect(SUPPORTED_EXTENSIONS.presentation).toContain("pptx");
-      expect(SUPPORTED_EXTENSIONS.presentation).toContain("odp");
-    });
-  });
-
   describe("getGoogleTypeFromExtension", () => {
     it("returns document for doc extensions", () => {
-      expect(getGoogleTypeFromExtension("doc")).toBe("document");
-      expect(getGoogleTypeFromExtension("docx")).toBe("document");
-      expect(getGoogleTypeFromExtension("odt")).toBe("document");
-      expect(getGoogleTypeFromExtension("rtf")).toBe("document");
-      expect(getGoogleTypeFromExtension("txt")).toBe("document");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "doc")).toBe("document");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "docx")).toBe("document");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "odt")).toBe("document");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "rtf")).toBe("document");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "txt")).toBe("document");
     });
 
     it("returns spreadsheet for spreadsheet extensions", () => {
-      expect(getGoogleTypeFromExtension("xls")).toBe("spreadsheet");
-      expect(getGoogleTypeFromExtension("xlsx")).toBe("spreadsheet");
-      expect(getGoogleTypeFromExtension("ods")).toBe("spreadsheet");
-      expect(getGoogleTypeFromExtension("csv")).toBe("spreadsheet");
-      expect(getGoogleTypeFromExtension("tsv")).toBe("spreadsheet");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "xls")).toBe("spreadsheet");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "xlsx")).toBe("spreadsheet");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "ods")).toBe("spreadsheet");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "csv")).toBe("spreadsheet");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "tsv")).toBe("spreadsheet");
     });
 
     it("returns presentation for presentation extensions", () => {
-      expect(getGoogleTypeFromExtension("ppt")).toBe("presentation");
-      expect(getGoogleTypeFromExtension("pptx")).toBe("presentation");
-      expect(getGoogleTypeFromExtension("odp")).toBe("presentation");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "ppt")).toBe("presentation");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "pptx")).toBe("presentation");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "odp")).toBe("presentation");
+    });
+
+    it("returns document for PDFs and images", () => {
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "pdf")).toBe("document");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "jpg")).toBe("document");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "png")).toBe("document");
     });
 
     it("returns null for unsupported extensions", () => {
-      expect(getGoogleTypeFromExtension("pdf")).toBeNull();
-      expect(getGoogleTypeFromExtension("jpg")).toBeNull();
-      expect(getGoogleTypeFromExtension("zip")).toBeNull();
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "svg")).toBeNull();
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "zip")).toBeNull();
     });
 
     it("handles extensions with leading dot", () => {
-      expect(getGoogleTypeFromExtension(".docx")).toBe("document");
-      expect(getGoogleTypeFromExtension(".xlsx")).toBe("spreadsheet");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, ".docx")).toBe("document");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, ".xlsx")).toBe("spreadsheet");
     });
 
     it("is case-insensitive", () => {
-      expect(getGoogleTypeFromExtension("DOCX")).toBe("document");
-      expect(getGoogleTypeFromExtension("Xlsx")).toBe("spreadsheet");
-      expect(getGoogleTypeFromExtension("PPTX")).toBe("presentation");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "DOCX")).toBe("document");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "Xlsx")).toBe("spreadsheet");
+      expect(getGoogleTypeFromExtension(TEST_FORMATS, "PPTX")).toBe("presentation");
     });
   });
 
@@ -117,32 +100,32 @@ describe("google-api", () => {
 
   describe("isSupportedExtension", () => {
     it("returns true for supported extensions", () => {
-      expect(isSupportedExtension("docx")).toBe(true);
-      expect(isSupportedExtension("xlsx")).toBe(true);
-      expect(isSupportedExtension("pptx")).toBe(true);
-      expect(isSupportedExtension("csv")).toBe(true);
-      expect(isSupportedExtension("txt")).toBe(true);
+      expect(isSupportedExtension(TEST_FORMATS, "docx")).toBe(true);
+      expect(isSupportedExtension(TEST_FORMATS, "xlsx")).toBe(true);
+      expect(isSupportedExtension(TEST_FORMATS, "pptx")).toBe(true);
+      expect(isSupportedExtension(TEST_FORMATS, "csv")).toBe(true);
+      expect(isSupportedExtension(TEST_FORMATS, "txt")).toBe(true);
     });
 
     it("returns false for unsupported extensions", () => {
-      expect(isSupportedExtension("pdf")).toBe(false);
-      expect(isSupportedExtension("jpg")).toBe(false);
-      expect(isSupportedExtension("mp4")).toBe(false);
+      expect(isSupportedExtension(TEST_FORMATS, "svg")).toBe(false);
+      expect(isSupportedExtension(TEST_FORMATS, "tiff")).toBe(false);
+      expect(isSupportedExtension(TEST_FORMATS, "mp4")).toBe(false);
     });
 
     it("handles leading dots", () => {
-      expect(isSupportedExtension(".docx")).toBe(true);
+      expect(isSupportedExtension(TEST_FORMATS, ".docx")).toBe(true);
     });
 
     it("is case-insensitive", () => {
-      expect(isSupportedExtension("DOCX")).toBe(true);
-      expect(isSupportedExtension("Xlsx")).toBe(true);
+      expect(isSupportedExtension(TEST_FORMATS, "DOCX")).toBe(true);
+      expect(isSupportedExtension(TEST_FORMATS, "Xlsx")).toBe(true);
     });
   });
 
   describe("getAllSupportedExtensions", () => {
     it("returns all extensions as a flat array", () => {
-      const extensions = getAllSupportedExtensions();
+      const extensions = getAllSupportedExtensions(TEST_FORMATS);
 
       expect(extensions).toContain("doc");
       expect(extensions).toContain("xlsx");
@@ -152,13 +135,8 @@ describe("google-api", () => {
     });
 
     it("returns the correct total count", () => {
-      const extensions = getAllSupportedExtensions();
-      const expectedCount =
-        SUPPORTED_EXTENSIONS.document.length +
-        SUPPORTED_EXTENSIONS.spreadsheet.length +
-        SUPPORTED_EXTENSIONS.presentation.length;
-
-      expect(extensions).toHaveLength(expectedCount);
+      const extensions = getAllSupportedExtensions(TEST_FORMATS);
+      expect(extensions).toHaveLength(TEST_FORMATS.length);
     });
   });
 });
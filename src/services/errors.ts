@@ -0,0 +1,29 @@
+/** Error every backend command rejects with */
+export interface GopenerError {
+  /** Stable identifier, e.g. "not_authenticated" or "quota_exceeded" */
+  code: string;
+  message: string;
+  /** Whether trying the same operation again may succeed */
+  retryable: boolean;
+  /** Reason Google gave for a failed API call, if any */
+  reason: string | null;
+}
+
+export function isGopenerError(e: unknown): e is GopenerError {
+  return (
+    typeof e === "object" &&
+    e !== null &&
+    typeof (e as GopenerError).code === "string" &&
+    typeof (e as GopenerError).message === "string"
+  );
+}
+
+/** Text to show for anything a command rejected with */
+export function errorMessage(e: unknown): string {
+  return isGopenerError(e) ? e.message : String(e);
+}
+
+/** Error code of a command rejection, or null for unexpected failures */
+export function errorCode(e: unknown): string | null {
+  return isGopenerError(e) ? e.code : null;
+}
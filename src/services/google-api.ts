@@ -2,6 +2,8 @@
 // Most Google API calls are handled by the Rust backend
 // This file provides TypeScript types and any frontend-specific helpers
 
+import type { GoogleFileType, SupportedFormat } from "./tauri-commands";
+
 export interface GoogleFile {
   id: string;
   name: string;
@@ -24,30 +26,21 @@ export const GOOGLE_MIME_TYPES = {
   folder: "application/vnd.google-apps.folder",
 } as const;
 
-// Supported file extensions
-export const SUPPORTED_EXTENSIONS = {
-  document: ["doc", "docx", "odt", "rtf", "txt"],
-  spreadsheet: ["xls", "xlsx", "ods", "csv", "tsv"],
-  presentation: ["ppt", "pptx", "odp"],
-} as const;
-
-// Get Google type from file extension
+// Google type for a file extension, looked up in the formats the backend supports
 export function getGoogleTypeFromExtension(
+  formats: SupportedFormat[],
   extension: string
-): keyof typeof GOOGLE_MIME_TYPES | null {
-  const ext = extension.toLowerCase().replace(".", "");
-
-  if (SUPPORTED_EXTENSIONS.document.includes(ext as any)) {
-    return "document";
-  }
-  if (SUPPORTED_EXTENSIONS.spreadsheet.includes(ext as any)) {
-    return "spreadsheet";
-  }
-  if (SUPPORTED_EXTENSIONS.presentation.includes(ext as any)) {
-    return "presentation";
-  }
+): GoogleFileType | null {
+  return findFormat(formats, extension)?.target ?? null;
+}
 
-  return null;
+// Format for an extension, with or without the dot, in any case
+export function findFormat(
+  formats: SupportedFormat[],
+  extension: string
+): SupportedFormat | null {
+  const ext = extension.toLowerCase().replace(".", "");
+  return formats.find((format) => format.extension === ext) ?? null;
 }
 
 // Get display name for Google type
@@ -88,20 +81,14 @@ export function getFileTypeIcon(type: string): string {
 }
 
 // Check if extension is supported
-export function isSupportedExtension(extension: string): boolean {
-  const ext = extension.toLowerCase().replace(".", "");
-  return (
-    SUPPORTED_EXTENSIONS.document.includes(ext as any) ||
-    SUPPORTED_EXTENSIONS.spreadsheet.includes(ext as any) ||
-    SUPPORTED_EXTENSIONS.presentation.includes(ext as any)
-  );
+export function isSupportedExtension(
+  formats: SupportedFormat[],
+  extension: string
+): boolean {
+  return findFormat(formats, extension) !== null;
 }
 
 // Get all supported extensions as a flat array
-export function getAllSupportedExtensions(): string[] {
-  return [
-    ...SUPPORTED_EXTENSIONS.document,
-    ...SUPPORTED_EXTENSIONS.spreadsheet,
-    ...SUPPORTED_EXTENSIONS.presentation,
-  ];
+export function getAllSupportedExtensions(formats: SupportedFormat[]): string[] {
+  return formats.map((format) => format.extension);
 }
@@ -1,16 +1,30 @@
 import { invoke } from "@tauri-apps/api/core";
+import type { GopenerError } from "./errors";
+
+export type { GopenerError } from "./errors";
 
 // Auth commands
 export async function getAuthUrl(): Promise<string> {
   return invoke("get_auth_url");
 }
 
-export async function exchangeCode(code: string): Promise<{
+export async function startSignIn(): Promise<string> {
+  return invoke("start_sign_in");
+}
+
+export async function cancelSignIn(): Promise<void> {
+  return invoke("cancel_sign_in");
+}
+
+export async function exchangeCode(
+  code: string,
+  state: string
+): Promise<{
   is_authenticated: boolean;
   access_token: string | null;
   expires_at: number | null;
 }> {
-  return invoke("exchange_code", { code });
+  return invoke("exchange_code", { code, state });
 }
 
 export async function refreshToken(): Promise<{
@@ -39,23 +53,238 @@ export interface UploadResult {
   name: string;
   web_view_link: string;
   file_type: string;
+  /** Converted to a Google format rather than kept as the original file */
+  converted: boolean;
+  /** A new revision of an earlier upload instead of a new file */
+  updated_existing: boolean;
+  /** Things to know about an upload that worked, such as a mismatched extension */
+  warning?: string;
 }
 
+/** What to do when the file was uploaded before */
+export type ExistingFileMode = "update" | "create" | "ask";
+
+/**
+ * Upload one file. `folderPath` such as "Clients/Acme/2026" is resolved below
+ * `folderId` (or My Drive), creating missing folders on the way. With "ask",
+ * a file uploaded before rejects with code "already_uploaded". `convert: false`
+ * keeps the original format; null uses the per-extension default in Settings.
+ * `ocrLanguage` such as "de" helps Drive read text in PDFs and images.
+ * The content decides the format; a file it can't identify rejects with code
+ * "unrecognized_content", one that needs a password to open with
+ * "password_protected", and one over Google's conversion limits with
+ * "conversion_limit_exceeded", before anything is sent. A `jobId` chosen here
+ * identifies the upload's progress events and can be cancelled right away.
+ */
 export async function uploadFile(
   filePath: string,
-  folderId?: string | null
+  folderId?: string | null,
+  folderPath?: string | null,
+  onExisting: ExistingFileMode = "ask",
+  convert: boolean | null = null,
+  ocrLanguage: string | null = null,
+  jobId: string | null = null
 ): Promise<UploadResult> {
-  return invoke("upload_file", { filePath, folderId });
+  return invoke("upload_file", {
+    filePath,
+    folderId: folderId ?? null,
+    folderPath: folderPath ?? null,
+    onExisting,
+    convert,
+    ocrLanguage,
+    jobId,
+  });
+}
+
+export type UploadJobState =
+  | "queued"
+  | "running"
+  | "succeeded"
+  | "failed"
+  | "cancelled";
+
+export interface UploadJob {
+  id: string;
+  batch_id: string | null;
+  file_path: string;
+  folder_id: string | null;
+  state: UploadJobState;
+  progress: {
+    job_id: string;
+    bytes_uploaded: number;
+    total_bytes: number;
+    percentage: number;
+    bytes_per_second: number;
+    eta_seconds: number | null;
+  };
+  result: UploadResult | null;
+  error: GopenerError | null;
+}
+
+export interface UploadOptions {
+  concurrency?: number;
+  /** Defaults to "ask", which fails files uploaded before with "already_uploaded" */
+  on_existing?: ExistingFileMode;
+  /** Convert to Google formats; defaults to the per-extension choice in Settings */
+  convert?: boolean;
+  /** Language of the text in PDFs and images; defaults to the one in Settings */
+  ocr_language?: string;
+}
+
+export interface FileUploadOutcome {
+  job_id: string;
+  file_path: string;
+  state: UploadJobState;
+  result: UploadResult | null;
+  error: GopenerError | null;
+}
+
+export interface BatchUploadResult {
+  batch_id: string;
+  files: FileUploadOutcome[];
+}
+
+/** Payload of the "upload-batch-progress" event */
+export interface BatchProgress {
+  batch_id: string;
+  total_files: number;
+  succeeded: number;
+  failed: number;
+  cancelled: number;
+  bytes_uploaded: number;
+  total_bytes: number;
+  percentage: number;
+}
+
+export async function uploadFiles(
+  paths: string[],
+  folderId?: string | null,
+  options?: UploadOptions,
+  folderPath?: string | null
+): Promise<BatchUploadResult> {
+  return invoke("upload_files", {
+    paths,
+    folderId: folderId ?? null,
+    folderPath: folderPath ?? null,
+    options: options ?? null,
+  });
+}
+
+export type UnsupportedFiles = "skip" | "upload_unconverted";
+
+export interface FolderUploadOptions {
+  unsupported?: UnsupportedFiles;
+  concurrency?: number;
+  ocr_language?: string;
+}
+
+export type FolderFileStatus =
+  | "converted"
+  | "unconverted"
+  | "skipped"
+  | "failed"
+  | "cancelled";
+
+export interface FileSummary {
+  name: string;
+  local_path: string;
+  status: FolderFileStatus;
+  job_id: string | null;
+  file_id: string | null;
+  web_view_link: string | null;
+  error: GopenerError | null;
+}
+
+export interface FolderSummary {
+  name: string;
+  local_path: string;
+  /** Null when this folder, or one above it, could not be created */
+  folder_id: string | null;
+  /** Why the folder was not created; its files are marked failed */
+  error: GopenerError | null;
+  files: FileSummary[];
+  folders: FolderSummary[];
+}
+
+export interface FolderUploadResult {
+  batch_id: string;
+  root: FolderSummary;
+  uploaded: number;
+  skipped: number;
+  failed: number;
+}
+
+export async function uploadFolder(
+  dirPath: string,
+  parentId?: string | null,
+  options?: FolderUploadOptions
+): Promise<FolderUploadResult> {
+  return invoke("upload_folder", {
+    dirPath,
+    parentId: parentId ?? null,
+    options: options ?? null,
+  });
+}
+
+export async function cancelUpload(jobId: string): Promise<void> {
+  return invoke("cancel_upload", { jobId });
+}
+
+export async function listUploadJobs(): Promise<UploadJob[]> {
+  return invoke("list_upload_jobs");
+}
+
+export async function getUploadJob(jobId: string): Promise<UploadJob> {
+  return invoke("get_upload_job", { jobId });
+}
+
+// Upload history commands
+export interface UploadRecord {
+  drive_id: string;
+  local_path: string;
+  content_hash: string | null;
+  size: number | null;
+  modified_at: number | null;
+  name: string;
+  mime_type: string | null;
+  file_type: string;
+  web_view_link: string;
+  folder_id: string | null;
+  first_uploaded_at: number;
+  last_uploaded_at: number;
+}
+
+export async function findUploadsByPath(
+  localPath: string
+): Promise<UploadRecord[]> {
+  return invoke("find_uploads_by_path", { localPath });
+}
+
+export async function findUploadByDriveId(
+  driveId: string
+): Promise<UploadRecord | null> {
+  return invoke("find_upload_by_drive_id", { driveId });
+}
+
+export async function listUploadsInFolder(
+  folderId: string
+): Promise<UploadRecord[]> {
+  return invoke("list_uploads_in_folder", { folderId });
 }
 
 // Storage commands
 export interface Settings {
   default_folder_id: string | null;
   default_folder_name: string | null;
+  default_folder_path: string | null;
   auto_open_after_upload: boolean;
   auto_close_after_upload: boolean;
   theme: string;
   recent_files: RecentFile[];
+  keep_original_extensions: string[];
+  ocr_language: string | null;
+  max_request_attempts: number | null;
+  max_retry_delay_secs: number | null;
 }
 
 export interface RecentFile {
@@ -105,6 +334,35 @@ export async function clearOAuthConfig(): Promise<void> {
   return invoke("clear_oauth_config");
 }
 
+// Format commands
+/** Google format a file converts to */
+export type GoogleFileType = "document" | "spreadsheet" | "presentation";
+
+export interface ConversionLimits {
+  /** Largest file Drive converts, in bytes */
+  max_file_size: number;
+  max_characters: number | null;
+  max_cells: number | null;
+  /** Size past which Drive converts less reliably, in bytes; a warning, not a limit */
+  recommended_file_size: number | null;
+}
+
+export interface SupportedFormat {
+  /** Lowercase, without the dot */
+  extension: string;
+  mime_type: string;
+  target: GoogleFileType;
+  /** Name of the format itself, such as "Microsoft Excel" */
+  name: string;
+  /** Converted by recognizing text, as for PDFs and images */
+  ocr: boolean;
+  limits: ConversionLimits;
+}
+
+export async function getSupportedFormats(): Promise<SupportedFormat[]> {
+  return invoke("get_supported_formats");
+}
+
 // File association commands
 export async function registerFileAssociations(): Promise<string> {
   return invoke("register_file_associations");
@@ -114,13 +372,70 @@ export async function registerFileAssociations(): Promise<string> {
 export interface DriveFolder {
   id: string;
   name: string;
-  mime_type: string;
+  mimeType: string;
+  /** Shared drive holding the folder; absent for My Drive */
+  driveId?: string;
+  /** Owner's display name; absent for shared drive folders */
+  owner?: string;
+  /** False for folders the user can only view */
+  canAddChildren: boolean;
+}
+
+export interface FolderSearchResult extends DriveFolder {
+  /** Folder names from the top of its drive down to the folder itself */
+  path: string[];
+}
+
+/** `recent` lists folders opened in the last 30 days, most recent first */
+export type FolderRoot = "shared_with_me" | "starred" | "recent";
+
+export interface SharedDrive {
+  id: string;
+  name: string;
+}
+
+export async function listSharedDrives(): Promise<SharedDrive[]> {
+  return invoke("list_shared_drives");
+}
+
+export interface FolderPage {
+  folders: DriveFolder[];
+  next_page_token: string | null;
 }
 
 export async function listFolders(
-  parentId?: string | null
+  parentId?: string | null,
+  pageToken?: string | null,
+  driveId?: string | null
+): Promise<FolderPage> {
+  return invoke("list_folders", {
+    parentId: parentId ?? null,
+    driveId: driveId ?? null,
+    pageToken: pageToken ?? null,
+  });
+}
+
+export async function listRootFolders(
+  root: FolderRoot,
+  pageToken?: string | null
+): Promise<FolderPage> {
+  return invoke("list_root_folders", { root, pageToken: pageToken ?? null });
+}
+
+export async function listAllFolders(
+  parentId?: string | null,
+  driveId?: string | null
 ): Promise<DriveFolder[]> {
-  return invoke("list_folders", { parentId });
+  return invoke("list_all_folders", {
+    parentId: parentId ?? null,
+    driveId: driveId ?? null,
+  });
+}
+
+export async function searchFolders(
+  query: string
+): Promise<FolderSearchResult[]> {
+  return invoke("search_folders", { query });
 }
 
 export async function createFolder(
@@ -2,16 +2,20 @@ import { describe, it, expect, beforeEach, vi } from "vitest";
 import { setActivePinia, createPinia } from "pinia";
 import { useAuthStore } from "../auth";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 vi.mock("@tauri-apps/api/core");
+vi.mock("@tauri-apps/api/event");
 vi.mock("@tauri-apps/plugin-shell");
 
 const mockedInvoke = vi.mocked(invoke);
+const mockedListen = vi.mocked(listen);
 
 describe("useAuthStore", () => {
   beforeEach(() => {
     setActivePinia(createPinia());
     vi.clearAllMocks();
+    mockedListen.mockResolvedValue(vi.fn());
   });
 
   it("has correct initial state", () => {
@@ -81,11 +85,43 @@ describe("useAuthStore", () => {
 
       const url = await store.signIn();
 
-      expect(mockedInvoke).toHaveBeenCalledWith("get_auth_url");
+      expect(mockedInvoke).toHaveBeenCalledWith("start_sign_in");
+      expect(mockedListen).toHaveBeenCalledWith(
+        "auth-completed",
+        expect.any(Function)
+      );
       expect(url).toBe("https://accounts.google.com/o/oauth2/...");
+      expect(store.isWaitingForBrowser).toBe(true);
       expect(store.isLoading).toBe(false);
     });
 
+    it("updates auth state when the backend completes sign-in", async () => {
+      const store = useAuthStore();
+      mockedInvoke.mockResolvedValueOnce("https://accounts.google.com/o/oauth2/...");
+
+      await store.signIn();
+
+      const handler = mockedListen.mock.calls[0][1];
+      handler({
+        event: "auth-completed",
+        id: 1,
+        payload: {
+          success: true,
+          cancelled: false,
+          auth_state: {
+            is_authenticated: true,
+            access_token: "loopback-token",
+            expires_at: 9999999999,
+          },
+          error: null,
+        },
+      });
+
+      expect(store.isAuthenticated).toBe(true);
+      expect(store.accessToken).toBe("loopback-token");
+      expect(store.isWaitingForBrowser).toBe(false);
+    });
+
     it("sets error on failure", async () => {
       const store = useAuthStore();
       mockedInvoke.mockRejectedValueOnce(new Error("OAuth failed"));
@@ -95,6 +131,19 @@ describe("useAuthStore", () => {
     });
   });
 
+  describe("cancelSignIn", () => {
+    it("cancels the pending loopback flow", async () => {
+      const store = useAuthStore();
+      store.isWaitingForBrowser = true;
+      mockedInvoke.mockResolvedValueOnce(undefined);
+
+      await store.cancelSignIn();
+
+      expect(mockedInvoke).toHaveBeenCalledWith("cancel_sign_in");
+      expect(store.isWaitingForBrowser).toBe(false);
+    });
+  });
+
   describe("handleCallback", () => {
     it("exchanges code and updates auth state", async () => {
       const store = useAuthStore();
@@ -105,10 +154,11 @@ describe("useAuthStore", () => {
       };
       mockedInvoke.mockResolvedValueOnce(mockState);
 
-      await store.handleCallback("auth-code-123");
+      await store.handleCallback("auth-code-123", "state-abc");
 
       expect(mockedInvoke).toHaveBeenCalledWith("exchange_code", {
         code: "auth-code-123",
+        state: "state-abc",
       });
       expect(store.isAuthenticated).toBe(true);
       expect(store.accessToken).toBe("new-token");
@@ -118,7 +168,7 @@ describe("useAuthStore", () => {
       const store = useAuthStore();
       mockedInvoke.mockRejectedValueOnce(new Error("Invalid code"));
 
-      await expect(store.handleCallback("bad-code")).rejects.toThrow(
+      await expect(store.handleCallback("bad-code", "state")).rejects.toThrow(
         "Invalid code"
       );
       expect(store.error).toBe("Error: Invalid code");
@@ -0,0 +1,39 @@
+import { describe, it, expect, beforeEach, vi } from "vitest";
+import { setActivePinia, createPinia } from "pinia";
+import { useFormatsStore } from "../formats";
+import { invoke } from "@tauri-apps/api/core";
+import { TEST_FORMATS } from "../../services/__tests__/formats.fixture";
+
+vi.mock("@tauri-apps/api/core");
+
+const mockedInvoke = vi.mocked(invoke);
+
+describe("useFormatsStore", () => {
+  beforeEach(() => {
+    setActivePinia(createPinia());
+    vi.clearAllMocks();
+  });
+
+  it("loads the formats once", async () => {
+    const store = useFormatsStore();
+    mockedInvoke.mockResolvedValueOnce(TEST_FORMATS);
+
+    await store.loadFormats();
+    await store.loadFormats();
+
+    expect(mockedInvoke).toHaveBeenCalledTimes(1);
+    expect(mockedInvoke).toHaveBeenCalledWith("get_supported_formats");
+    expect(store.extensions).toContain("xlsb");
+  });
+
+  it("looks up files by extension", () => {
+    const store = useFormatsStore();
+    store.formats = TEST_FORMATS;
+
+    expect(store.targetName("C:\\Reports\\Budget.XLSM")).toBe("Google Sheets");
+    expect(store.targetName("/scans/receipt.jpeg")).toBe("Google Docs");
+    expect(store.formatOf("/scans/receipt.jpeg")?.ocr).toBe(true);
+    expect(store.targetName("/docs/notes")).toBeNull();
+    expect(store.targetName("/img/logo.svg")).toBeNull();
+  });
+});
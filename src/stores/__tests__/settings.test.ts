@@ -101,10 +101,13 @@ describe("useSettingsStore", () => {
         settings: {
           default_folder_id: "folder-abc",
           default_folder_name: null,
+          default_folder_path: null,
           auto_open_after_upload: true,
           auto_close_after_upload: false,
           theme: "light",
           recent_files: [],
+          keep_original_extensions: [],
+          ocr_language: null,
         },
       });
     });
@@ -123,6 +126,60 @@ describe("useSettingsStore", () => {
     });
   });
 
+  describe("setKeepOriginalExtensions", () => {
+    it("normalizes extensions and matches files by them", async () => {
+      const store = useSettingsStore();
+      mockedInvoke.mockResolvedValue(undefined);
+
+      await store.setKeepOriginalExtensions([" .XLSM", "docx", "", "xlsm"]);
+
+      expect(store.keepOriginalExtensions).toEqual(["xlsm", "docx"]);
+      expect(store.keepsOriginal("C:\\Reports\\Budget.XLSM")).toBe(true);
+      expect(store.keepsOriginal("/docs/letter.docx")).toBe(true);
+      expect(store.keepsOriginal("/docs/deck.pptx")).toBe(false);
+      expect(store.keepsOriginal("/docs/docx")).toBe(false);
+      expect(mockedInvoke).toHaveBeenCalledWith("save_settings", expect.any(Object));
+    });
+  });
+
+  describe("setDefaultFolderPath", () => {
+    it("stores the normalized path and names the folder after its last segment", async () => {
+      const store = useSettingsStore();
+      store.defaultFolderId = "old-folder";
+      mockedInvoke.mockResolvedValue(undefined);
+
+      await store.setDefaultFolderPath("/Clients// Acme /");
+
+      expect(store.defaultFolderPath).toBe("Clients/Acme");
+      expect(store.defaultFolderName).toBe("Acme");
+      expect(store.defaultFolderId).toBeNull();
+      expect(store.destination).toEqual({
+        folderId: null,
+        folderPath: "Clients/Acme",
+      });
+    });
+
+    it("keeps using a picked folder's ID over its path", async () => {
+      const store = useSettingsStore();
+      mockedInvoke.mockResolvedValue(undefined);
+
+      await store.setDefaultFolder("folder-1", "Acme", "Clients/Acme");
+
+      expect(store.destination).toEqual({ folderId: "folder-1", folderPath: null });
+    });
+
+    it("falls back to My Drive for an empty path", async () => {
+      const store = useSettingsStore();
+      store.defaultFolderPath = "Clients";
+      mockedInvoke.mockResolvedValue(undefined);
+
+      await store.setDefaultFolderPath(" / ");
+
+      expect(store.defaultFolderPath).toBeNull();
+      expect(store.defaultFolderName).toBeNull();
+    });
+  });
+
   describe("setAutoOpenAfterUpload", () => {
     it("updates preference and saves", async () => {
       const store = useSettingsStore();
@@ -1,6 +1,8 @@
 import { describe, it, expect, beforeEach, vi } from "vitest";
 import { setActivePinia, createPinia } from "pinia";
 import { useUploadStore } from "../upload";
+import { useFormatsStore } from "../formats";
+import { TEST_FORMATS } from "../../services/__tests__/formats.fixture";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 
@@ -10,10 +12,23 @@ vi.mock("@tauri-apps/api/event");
 const mockedInvoke = vi.mocked(invoke);
 const mockedListen = vi.mocked(listen);
 
+type Handler = (event: { payload: unknown }) => void;
+
+function captureListeners(): Record<string, Handler> {
+  const handlers: Record<string, Handler> = {};
+  mockedListen.mockImplementation(async (event, handler) => {
+    handlers[event as string] = handler as Handler;
+    return vi.fn();
+  });
+  return handlers;
+}
+
 describe("useUploadStore", () => {
   beforeEach(() => {
     setActivePinia(createPinia());
     vi.clearAllMocks();
+    useFormatsStore().formats = TEST_FORMATS;
+    mockedInvoke.mockReset();
     mockedListen.mockResolvedValue(vi.fn());
   });
 
@@ -26,9 +41,12 @@ describe("useUploadStore", () => {
     expect(store.fileSize).toBe(0);
     expect(store.status).toBe("idle");
     expect(store.progress).toEqual({
+      job_id: null,
       bytes_uploaded: 0,
       total_bytes: 0,
       percentage: 0,
+      bytes_per_second: 0,
+      eta_seconds: null,
     });
     expect(store.result).toBeNull();
     expect(store.error).toBeNull();
@@ -51,6 +69,28 @@ describe("useUploadStore", () => {
     expect(store.hasError).toBe(true);
   });
 
+  it("formats throughput and time remaining", () => {
+    const store = useUploadStore();
+    expect(store.progressDetail).toBe("");
+
+    store.progress = {
+      job_id: "upload-1",
+      bytes_uploaded: 1024 * 1024,
+      total_bytes: 10 * 1024 * 1024,
+      percentage: 10,
+      bytes_per_second: 2.5 * 1024 * 1024,
+      eta_seconds: 90,
+    };
+    expect(store.progressDetail).toBe("2.5 MB/s · 1m 30s left");
+
+    store.progress = {
+      ...store.progress,
+      bytes_per_second: 512 * 1024,
+      eta_seconds: 4.2,
+    };
+    expect(store.progressDetail).toBe("512 KB/s · 5s left");
+  });
+
   describe("setFile", () => {
     it("parses file path and detects document type", () => {
       const store = useUploadStore();
@@ -87,6 +127,16 @@ describe("useUploadStore", () => {
       expect(store.fileType).toBe("Google Docs");
     });
 
+    it("handles macro-enabled, template and binary variants", () => {
+      const store = useUploadStore();
+      store.setFile("/test/letter.dotx");
+      expect(store.fileType).toBe("Google Docs");
+      store.setFile("/test/model.xlsb");
+      expect(store.fileType).toBe("Google Sheets");
+      store.setFile("/test/show.PPSX");
+      expect(store.fileType).toBe("Google Slides");
+    });
+
     it("handles .csv extension", () => {
       const store = useUploadStore();
       store.setFile("/data/export.csv");
@@ -116,9 +166,12 @@ describe("useUploadStore", () => {
       expect(store.error).toBeNull();
       expect(store.result).toBeNull();
       expect(store.progress).toEqual({
+        job_id: null,
         bytes_uploaded: 0,
         total_bytes: 0,
         percentage: 0,
+        bytes_per_second: 0,
+        eta_seconds: null,
       });
     });
   });
@@ -141,6 +194,11 @@ describe("useUploadStore", () => {
       expect(mockedInvoke).toHaveBeenCalledWith("upload_file", {
         filePath: "/test/report.docx",
         folderId: "folder-id",
+        folderPath: null,
+        onExisting: "ask",
+        convert: null,
+        ocrLanguage: null,
+        jobId: expect.any(String),
       });
       expect(store.result).toEqual(mockResult);
       expect(store.status).toBe("success");
@@ -163,9 +221,134 @@ describe("useUploadStore", () => {
       await store.upload();
 
       expect(store.error).toBe("Error: Upload failed");
+      expect(store.errorCode).toBeNull();
       expect(store.status).toBe("error");
     });
 
+    it("keeps the code of a backend error", async () => {
+      const store = useUploadStore();
+      store.setFile("/test/report.docx");
+      mockedInvoke.mockRejectedValueOnce({
+        code: "not_authenticated",
+        message: "Not signed in to Google",
+        retryable: false,
+        reason: null,
+      });
+
+      await store.upload();
+
+      expect(store.error).toBe("Not signed in to Google");
+      expect(store.errorCode).toBe("not_authenticated");
+      expect(store.status).toBe("error");
+    });
+
+    it("waits for a choice when the file was uploaded before", async () => {
+      const store = useUploadStore();
+      store.setFile("/test/report.docx");
+      mockedInvoke.mockRejectedValueOnce({
+        code: "already_uploaded",
+        message: "report was already uploaded to Google Drive",
+        retryable: false,
+        reason: null,
+      });
+
+      await store.upload();
+
+      expect(store.isAlreadyUploaded).toBe(true);
+      expect(store.hasError).toBe(false);
+
+      mockedInvoke.mockResolvedValueOnce({
+        file_id: "abc123",
+        name: "report",
+        web_view_link: "https://docs.google.com/document/d/abc123/edit",
+        file_type: "Google Docs",
+        updated_existing: true,
+      });
+
+      await store.upload(null, null, "update");
+
+      expect(mockedInvoke).toHaveBeenLastCalledWith("upload_file", {
+        filePath: "/test/report.docx",
+        folderId: null,
+        folderPath: null,
+        onExisting: "update",
+        convert: null,
+        ocrLanguage: null,
+        jobId: expect.any(String),
+      });
+      expect(store.result?.updated_existing).toBe(true);
+      expect(store.status).toBe("success");
+    });
+
+    it("follows progress of its own job only", async () => {
+      const store = useUploadStore();
+      store.setFile("/test/report.docx");
+
+      const handlers = captureListeners();
+      const onProgress = (event: { payload: unknown }) =>
+        handlers["upload-progress"]?.(event);
+
+      const progress = (jobId: string, bytes: number) => ({
+        payload: {
+          job_id: jobId,
+          bytes_uploaded: bytes,
+          total_bytes: 100,
+          percentage: bytes,
+          bytes_per_second: 0,
+          eta_seconds: null,
+        },
+      });
+
+      mockedInvoke.mockImplementationOnce(async (_command, args) => {
+        const { jobId } = args as { jobId: string };
+        expect(store.jobId).toBe(jobId);
+        // Another window's upload reports first and must not be taken over
+        onProgress(progress("upload-8", 90));
+        onProgress(progress(jobId, 10));
+        expect(store.jobId).toBe(jobId);
+        expect(store.progress.bytes_uploaded).toBe(10);
+        return {
+          file_id: "x",
+          name: "report",
+          web_view_link: "https://...",
+          file_type: "Google Docs",
+        };
+      });
+
+      await store.upload();
+
+      expect(store.status).toBe("success");
+    });
+
+    it("treats a cancelled upload as cancelled, not failed", async () => {
+      const store = useUploadStore();
+      store.setFile("/test/big.xlsx");
+      const handlers = captureListeners();
+
+      mockedInvoke.mockImplementation(async (command) => {
+        if (command === "cancel_upload") {
+          handlers["upload-cancelled"]?.({ payload: { job_id: store.jobId } });
+          return undefined;
+        }
+        // No progress yet; the job can be cancelled all the same
+        await store.cancel();
+        throw {
+          code: "cancelled",
+          message: "Upload cancelled",
+          retryable: false,
+          reason: null,
+        };
+      });
+
+      await store.upload();
+
+      const { jobId } = mockedInvoke.mock.calls[0][1] as { jobId: string };
+      expect(mockedInvoke).toHaveBeenCalledWith("cancel_upload", { jobId });
+      expect(store.status).toBe("cancelled");
+      expect(store.isCancelled).toBe(true);
+      expect(store.error).toBeNull();
+    });
+
     it("passes null folderId when not provided", async () => {
       const store = useUploadStore();
       store.setFile("/test/file.xlsx");
@@ -181,7 +364,61 @@ describe("useUploadStore", () => {
       expect(mockedInvoke).toHaveBeenCalledWith("upload_file", {
         filePath: "/test/file.xlsx",
         folderId: null,
+        folderPath: null,
+        onExisting: "ask",
+        convert: null,
+        ocrLanguage: null,
+        jobId: expect.any(String),
+      });
+    });
+
+    it("passes the destination path to the backend", async () => {
+      const store = useUploadStore();
+      store.setFile("/test/file.xlsx");
+      mockedInvoke.mockResolvedValueOnce({
+        file_id: "x",
+        name: "file",
+        web_view_link: "https://...",
+        file_type: "Google Sheets",
+      });
+
+      await store.upload(null, "Clients/Acme");
+
+      expect(mockedInvoke).toHaveBeenCalledWith("upload_file", {
+        filePath: "/test/file.xlsx",
+        folderId: null,
+        folderPath: "Clients/Acme",
+        onExisting: "ask",
+        convert: null,
+        ocrLanguage: null,
+        jobId: expect.any(String),
+      });
+    });
+
+    it("can keep the original format", async () => {
+      const store = useUploadStore();
+      store.setFile("/test/macros.xlsx");
+      mockedInvoke.mockResolvedValueOnce({
+        file_id: "x",
+        name: "macros.xlsx",
+        web_view_link: "https://drive.google.com/file/d/x/view",
+        file_type: "Microsoft Excel",
+        converted: false,
+        updated_existing: false,
+      });
+
+      await store.upload(null, null, "ask", false);
+
+      expect(mockedInvoke).toHaveBeenCalledWith("upload_file", {
+        filePath: "/test/macros.xlsx",
+        folderId: null,
+        folderPath: null,
+        onExisting: "ask",
+        convert: false,
+        ocrLanguage: null,
+        jobId: expect.any(String),
       });
+      expect(store.result?.file_type).toBe("Microsoft Excel");
     });
   });
 
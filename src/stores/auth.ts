@@ -1,6 +1,8 @@
 import { defineStore } from "pinia";
 import { ref, computed } from "vue";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { errorMessage, type GopenerError } from "../services/errors";
 
 interface AuthState {
   is_authenticated: boolean;
@@ -8,12 +10,22 @@ interface AuthState {
   expires_at: number | null;
 }
 
+interface AuthCompleted {
+  success: boolean;
+  cancelled: boolean;
+  auth_state: AuthState | null;
+  error: GopenerError | null;
+}
+
 export const useAuthStore = defineStore("auth", () => {
   const isAuthenticated = ref(false);
   const accessToken = ref<string | null>(null);
   const expiresAt = ref<number | null>(null);
   const isLoading = ref(false);
   const error = ref<string | null>(null);
+  const isWaitingForBrowser = ref(false);
+
+  let unlistenAuthCompleted: (() => void) | null = null;
 
   const isExpired = computed(() => {
     if (!expiresAt.value) return true;
@@ -31,48 +43,81 @@ export const useAuthStore = defineStore("auth", () => {
       accessToken.value = state.access_token;
       expiresAt.value = state.expires_at;
     } catch (e) {
-      error.value = String(e);
+      error.value = errorMessage(e);
       isAuthenticated.value = false;
     } finally {
       isLoading.value = false;
     }
   }
 
+  async function setupAuthListener() {
+    if (unlistenAuthCompleted) return;
+
+    unlistenAuthCompleted = await listen<AuthCompleted>(
+      "auth-completed",
+      (event) => {
+        const outcome = event.payload;
+        isWaitingForBrowser.value = false;
+
+        if (outcome.success && outcome.auth_state) {
+          isAuthenticated.value = outcome.auth_state.is_authenticated;
+          accessToken.value = outcome.auth_state.access_token;
+          expiresAt.value = outcome.auth_state.expires_at;
+        } else if (!outcome.cancelled) {
+          error.value = outcome.error?.message ?? null;
+        }
+      }
+    );
+  }
+
   async function signIn() {
     isLoading.value = true;
     error.value = null;
 
     try {
-      // Get the OAuth URL
-      const authUrl = await invoke<string>("get_auth_url");
+      await setupAuthListener();
+
+      // Start the loopback listener and get the OAuth URL; the backend
+      // exchanges the code itself and emits "auth-completed"
+      const authUrl = await invoke<string>("start_sign_in");
+      isWaitingForBrowser.value = true;
 
       // Open in default browser
       const { open } = await import("@tauri-apps/plugin-shell");
       await open(authUrl);
 
-      // Start a local server to receive the callback
-      // For simplicity, we'll prompt the user to paste the code
-      // In production, you'd use a local HTTP server
       return authUrl;
     } catch (e) {
-      error.value = String(e);
+      isWaitingForBrowser.value = false;
+      error.value = errorMessage(e);
       throw e;
     } finally {
       isLoading.value = false;
     }
   }
 
-  async function handleCallback(code: string) {
+  async function cancelSignIn() {
+    try {
+      await invoke("cancel_sign_in");
+    } finally {
+      isWaitingForBrowser.value = false;
+    }
+  }
+
+  async function handleCallback(code: string, state: string) {
     isLoading.value = true;
     error.value = null;
 
     try {
-      const state = await invoke<AuthState>("exchange_code", { code });
+      const state = await invoke<AuthState>("exchange_code", {
+        code,
+        state,
+      });
       isAuthenticated.value = state.is_authenticated;
       accessToken.value = state.access_token;
       expiresAt.value = state.expires_at;
     } catch (e) {
-      error.value = String(e);
+      error.value = errorMessage(e);
       throw e;
     } finally {
       isLoading.value = false;
@@ -86,7 +131,7 @@ export const useAuthStore = defineStore("auth", () => {
       accessToken.value = state.access_token;
       expiresAt.value = state.expires_at;
     } catch (e) {
-      error.value = String(e);
+      error.value = errorMessage(e);
       isAuthenticated.value = false;
     }
   }
@@ -101,7 +146,7 @@ export const useAuthStore = defineStore("auth", () => {
       accessToken.value = null;
       expiresAt.value = null;
     } catch (e) {
-      error.value = String(e);
+      error.value = errorMessage(e);
     } finally {
       isLoading.value = false;
     }
@@ -113,9 +158,11 @@ export const useAuthStore = defineStore("auth", () => {
     expiresAt,
     isLoading,
     error,
+    isWaitingForBrowser,
     isExpired,
     checkAuth,
     signIn,
+    cancelSignIn,
     handleCallback,
     refreshToken,
     signOut,
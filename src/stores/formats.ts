@@ -0,0 +1,47 @@
+import { defineStore } from "pinia";
+import { computed, ref } from "vue";
+import { invoke } from "@tauri-apps/api/core";
+import type { SupportedFormat } from "../services/tauri-commands";
+import {
+  findFormat,
+  getAllSupportedExtensions,
+  getGoogleTypeDisplayName,
+} from "../services/google-api";
+
+/** The formats the backend uploads; the frontend keeps no list of its own */
+export const useFormatsStore = defineStore("formats", () => {
+  const formats = ref<SupportedFormat[]>([]);
+
+  const extensions = computed(() => getAllSupportedExtensions(formats.value));
+
+  async function loadFormats() {
+    if (formats.value.length > 0) return;
+    try {
+      formats.value = await invoke<SupportedFormat[]>("get_supported_formats");
+    } catch (e) {
+      console.error("Failed to load supported formats:", e);
+    }
+  }
+
+  /** Format of a file path or name, judged by its extension */
+  function formatOf(path: string): SupportedFormat | null {
+    const name = path.split(/[/\\]/).pop() ?? "";
+    const dot = name.lastIndexOf(".");
+    if (dot <= 0) return null;
+    return findFormat(formats.value, name.slice(dot + 1));
+  }
+
+  /** Name of the Google app a file converts to, such as "Google Docs" */
+  function targetName(path: string): string | null {
+    const format = formatOf(path);
+    return format ? getGoogleTypeDisplayName(format.target) : null;
+  }
+
+  return {
+    formats,
+    extensions,
+    loadFormats,
+    formatOf,
+    targetName,
+  };
+});
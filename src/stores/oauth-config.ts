@@ -1,6 +1,7 @@
 import { defineStore } from "pinia";
 import { ref } from "vue";
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "../services/errors";
 
 export interface OAuthConfig {
   use_custom: boolean;
@@ -25,7 +26,7 @@ export const useOAuthConfigStore = defineStore("oauth-config", () => {
       clientId.value = config.client_id;
       hasClientSecret.value = config.has_client_secret;
     } catch (e) {
-      error.value = String(e);
+      error.value = errorMessage(e);
     } finally {
       isLoading.value = false;
     }
@@ -44,7 +45,7 @@ export const useOAuthConfigStore = defineStore("oauth-config", () => {
       clientId.value = newClientId;
       hasClientSecret.value = !!clientSecret;
     } catch (e) {
-      error.value = String(e);
+      error.value = errorMessage(e);
       throw e;
     } finally {
       isLoading.value = false;
@@ -61,7 +62,7 @@ export const useOAuthConfigStore = defineStore("oauth-config", () => {
       clientId.value = null;
       hasClientSecret.value = false;
     } catch (e) {
-      error.value = String(e);
+      error.value = errorMessage(e);
     } finally {
       isLoading.value = false;
     }
@@ -1,5 +1,5 @@
 import { defineStore } from "pinia";
-import { ref } from "vue";
+import { computed, ref } from "vue";
 import { invoke } from "@tauri-apps/api/core";
 
 export interface RecentFile {
@@ -14,31 +14,66 @@ export interface RecentFile {
 export interface Settings {
   default_folder_id: string | null;
   default_folder_name: string | null;
+  /** Readable destination such as "Clients/Acme"; resolved on upload when no ID is saved */
+  default_folder_path: string | null;
   auto_open_after_upload: boolean;
   auto_close_after_upload: boolean;
   theme: string;
   recent_files: RecentFile[];
+  /** Extensions such as "xlsm" uploaded as they are instead of converted */
+  keep_original_extensions: string[];
+  /** Language of the text in PDFs and images, such as "en" */
+  ocr_language: string | null;
+  /** Tries per request to Google, including the first; null for the default */
+  max_request_attempts: number | null;
+  /** Longest wait between two tries, in seconds; null for the default */
+  max_retry_delay_secs: number | null;
+}
+
+/** Lowercase extensions without leading dots, each listed once */
+export function normalizeExtensions(extensions: string[]): string[] {
+  const normalized = extensions
+    .map((ext) => ext.trim().replace(/^\.+/, "").toLowerCase())
+    .filter((ext) => ext.length > 0);
+  return [...new Set(normalized)];
 }
 
 export const useSettingsStore = defineStore("settings", () => {
   const defaultFolderId = ref<string | null>(null);
   const defaultFolderName = ref<string | null>(null);
+  const defaultFolderPath = ref<string | null>(null);
   const autoOpenAfterUpload = ref(true);
   const autoCloseAfterUpload = ref(false);
   const theme = ref("dark");
   const recentFiles = ref<RecentFile[]>([]);
+  const keepOriginalExtensions = ref<string[]>([]);
+  const ocrLanguage = ref<string | null>(null);
+  const maxRequestAttempts = ref<number | null>(null);
+  const maxRetryDelaySecs = ref<number | null>(null);
   const isLoading = ref(false);
 
+  /** Where uploads go: the picked folder's ID, otherwise the typed path */
+  const destination = computed(() =>
+    defaultFolderId.value
+      ? { folderId: defaultFolderId.value, folderPath: null }
+      : { folderId: null, folderPath: defaultFolderPath.value }
+  );
+
   async function loadSettings() {
     isLoading.value = true;
     try {
       const settings = await invoke<Settings>("get_settings");
       defaultFolderId.value = settings.default_folder_id;
       defaultFolderName.value = settings.default_folder_name;
+      defaultFolderPath.value = settings.default_folder_path ?? null;
       autoOpenAfterUpload.value = settings.auto_open_after_upload;
       autoCloseAfterUpload.value = settings.auto_close_after_upload;
       theme.value = settings.theme || "dark";
       recentFiles.value = settings.recent_files || [];
+      keepOriginalExtensions.value = settings.keep_original_extensions ?? [];
+      ocrLanguage.value = settings.ocr_language ?? null;
+      maxRequestAttempts.value = settings.max_request_attempts ?? null;
+      maxRetryDelaySecs.value = settings.max_retry_delay_secs ?? null;
     } catch (e) {
       console.error("Failed to load settings:", e);
     } finally {
@@ -52,10 +87,15 @@ export const useSettingsStore = defineStore("settings", () => {
         settings: {
           default_folder_id: defaultFolderId.value,
           default_folder_name: defaultFolderName.value,
+          default_folder_path: defaultFolderPath.value,
           auto_open_after_upload: autoOpenAfterUpload.value,
           auto_close_after_upload: autoCloseAfterUpload.value,
           theme: theme.value,
           recent_files: recentFiles.value,
+          keep_original_extensions: keepOriginalExtensions.value,
+          ocr_language: ocrLanguage.value,
+          max_request_attempts: maxRequestAttempts.value,
+          max_retry_delay_secs: maxRetryDelaySecs.value,
         },
       });
     } catch (e) {
@@ -63,9 +103,57 @@ export const useSettingsStore = defineStore("settings", () => {
     }
   }
 
-  async function setDefaultFolder(id: string | null, name: string | null) {
+  async function setDefaultFolder(
+    id: string | null,
+    name: string | null,
+    path: string | null = null
+  ) {
     defaultFolderId.value = id;
     defaultFolderName.value = name;
+    defaultFolderPath.value = path;
+    await saveSettings();
+  }
+
+  /** Use a My Drive path as the default destination; folders are created on upload */
+  async function setDefaultFolderPath(path: string) {
+    const segments = path
+      .split("/")
+      .map((segment) => segment.trim())
+      .filter((segment) => segment.length > 0);
+    if (segments.length === 0) {
+      await setDefaultFolder(null, null);
+      return;
+    }
+    await setDefaultFolder(null, segments[segments.length - 1], segments.join("/"));
+  }
+
+  /** Whether files like `path` upload without conversion unless told otherwise */
+  function keepsOriginal(path: string): boolean {
+    const name = path.split(/[/\\]/).pop() ?? "";
+    const dot = name.lastIndexOf(".");
+    if (dot <= 0) return false;
+    return keepOriginalExtensions.value.includes(name.slice(dot + 1).toLowerCase());
+  }
+
+  async function setKeepOriginalExtensions(extensions: string[]) {
+    keepOriginalExtensions.value = normalizeExtensions(extensions);
+    await saveSettings();
+  }
+
+  async function setOcrLanguage(language: string) {
+    ocrLanguage.value = language.trim() || null;
+    await saveSettings();
+  }
+
+  /** Retry limits for requests to Google; null restores a default */
+  async function setRetryLimits(
+    attempts: number | null,
+    delaySecs: number | null
+  ) {
+    maxRequestAttempts.value =
+      attempts === null ? null : Math.max(1, Math.round(attempts));
+    maxRetryDelaySecs.value =
+      delaySecs === null ? null : Math.max(0, Math.round(delaySecs));
     await saveSettings();
   }
 
@@ -106,14 +194,25 @@ export const useSettingsStore = defineStore("settings", () => {
   return {
     defaultFolderId,
     defaultFolderName,
+    defaultFolderPath,
+    destination,
     autoOpenAfterUpload,
     autoCloseAfterUpload,
     theme,
     recentFiles,
+    keepOriginalExtensions,
+    ocrLanguage,
+    maxRequestAttempts,
+    maxRetryDelaySecs,
     isLoading,
     loadSettings,
     saveSettings,
     setDefaultFolder,
+    setDefaultFolderPath,
+    keepsOriginal,
+    setKeepOriginalExtensions,
+    setOcrLanguage,
+    setRetryLimits,
     setAutoOpenAfterUpload,
     setAutoCloseAfterUpload,
     setTheme,
@@ -2,21 +2,74 @@ import { defineStore } from "pinia";
 import { ref, computed } from "vue";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { errorCode as codeOf, errorMessage } from "../services/errors";
+import { useFormatsStore } from "./formats";
 
 export interface UploadProgress {
+  job_id: string | null;
   bytes_uploaded: number;
   total_bytes: number;
   percentage: number;
+  bytes_per_second: number;
+  eta_seconds: number | null;
+}
+
+function emptyProgress(): UploadProgress {
+  return {
+    job_id: null,
+    bytes_uploaded: 0,
+    total_bytes: 0,
+    percentage: 0,
+    bytes_per_second: 0,
+    eta_seconds: null,
+  };
+}
+
+/** ID for a job started from this window, unique across windows */
+function newJobId(): string {
+  const random = Math.random().toString(36).slice(2, 10);
+  return `window-${Date.now().toString(36)}-${random}`;
+}
+
+function formatRate(bytesPerSecond: number): string {
+  if (bytesPerSecond >= 1024 * 1024) {
+    return `${(bytesPerSecond / (1024 * 1024)).toFixed(1)} MB/s`;
+  }
+  return `${Math.round(bytesPerSecond / 1024)} KB/s`;
+}
+
+function formatEta(seconds: number): string {
+  const rounded = Math.ceil(seconds);
+  if (rounded >= 60) {
+    return `${Math.floor(rounded / 60)}m ${rounded % 60}s left`;
+  }
+  return `${rounded}s left`;
 }
 
 export interface UploadResult {
   file_id: string;
   name: string;
   web_view_link: string;
+  /** Google format the file became, or the original format it was kept in */
   file_type: string;
+  /** Converted to a Google format rather than kept as the original file */
+  converted: boolean;
+  /** A new revision of an earlier upload instead of a new file */
+  updated_existing: boolean;
+  /** Things to know about an upload that worked, such as a mismatched extension */
+  warning?: string;
 }
 
-export type UploadStatus = "idle" | "uploading" | "success" | "error";
+/** What to do when the file was uploaded before */
+export type ExistingFileMode = "update" | "create" | "ask";
+
+export type UploadStatus =
+  | "idle"
+  | "uploading"
+  | "success"
+  | "error"
+  | "cancelled"
+  | "already-uploaded";
 
 export const useUploadStore = defineStore("upload", () => {
   const filePath = ref<string | null>(null);
@@ -25,27 +78,49 @@ export const useUploadStore = defineStore("upload", () => {
   const fileSize = ref<number>(0);
 
   const status = ref<UploadStatus>("idle");
-  const progress = ref<UploadProgress>({
-    bytes_uploaded: 0,
-    total_bytes: 0,
-    percentage: 0,
-  });
+  const progress = ref<UploadProgress>(emptyProgress());
+  const jobId = ref<string | null>(null);
   const result = ref<UploadResult | null>(null);
   const error = ref<string | null>(null);
+  const errorCode = ref<string | null>(null);
 
   const isUploading = computed(() => status.value === "uploading");
   const isComplete = computed(() => status.value === "success");
   const hasError = computed(() => status.value === "error");
+  const isCancelled = computed(() => status.value === "cancelled");
+  /** Waiting for the user to choose between updating the earlier upload or a new copy */
+  const isAlreadyUploaded = computed(() => status.value === "already-uploaded");
+  const progressDetail = computed(() => {
+    const { bytes_per_second, eta_seconds } = progress.value;
+    if (bytes_per_second <= 0) return "";
+    const parts = [formatRate(bytes_per_second)];
+    if (eta_seconds !== null) parts.push(formatEta(eta_seconds));
+    return parts.join(" · ");
+  });
 
   // Set up progress listener
   let unlistenProgress: (() => void) | null = null;
+  let unlistenCancelled: (() => void) | null = null;
+  let cancelRequested = false;
 
   async function setupProgressListener() {
     if (unlistenProgress) return;
 
+    unlistenCancelled = await listen<{ job_id: string }>(
+      "upload-cancelled",
+      (event) => {
+        if (event.payload.job_id === jobId.value) {
+          status.value = "cancelled";
+        }
+      }
+    );
+
     unlistenProgress = await listen<UploadProgress>(
       "upload-progress",
       (event) => {
+        // Every window hears every upload; follow only the one started here
+        if (status.value !== "uploading") return;
+        if (event.payload.job_id !== jobId.value) return;
         progress.value = event.payload;
       }
     );
@@ -59,25 +134,23 @@ export const useUploadStore = defineStore("upload", () => {
     fileName.value = parts[parts.length - 1];
 
     // Detect file type from extension
-    const ext = fileName.value.split(".").pop()?.toLowerCase();
-    if (ext) {
-      if (["doc", "docx", "odt", "rtf", "txt"].includes(ext)) {
-        fileType.value = "Google Docs";
-      } else if (["xls", "xlsx", "ods", "csv", "tsv"].includes(ext)) {
-        fileType.value = "Google Sheets";
-      } else if (["ppt", "pptx", "odp"].includes(ext)) {
-        fileType.value = "Google Slides";
-      }
-    }
+    fileType.value = useFormatsStore().targetName(path);
 
     // Reset status
     status.value = "idle";
     result.value = null;
     error.value = null;
-    progress.value = { bytes_uploaded: 0, total_bytes: 0, percentage: 0 };
+    errorCode.value = null;
+    progress.value = emptyProgress();
   }
 
-  async function upload(folderId?: string | null) {
+  async function upload(
+    folderId?: string | null,
+    folderPath?: string | null,
+    onExisting: ExistingFileMode = "ask",
+    convert: boolean | null = null,
+    ocrLanguage: string | null = null
+  ) {
     if (!filePath.value) {
       error.value = "No file selected";
       status.value = "error";
@@ -88,19 +161,50 @@ export const useUploadStore = defineStore("upload", () => {
 
     status.value = "uploading";
     error.value = null;
-    progress.value = { bytes_uploaded: 0, total_bytes: 0, percentage: 0 };
+    errorCode.value = null;
+    // Naming the job up front lets cancel() reach it before any progress arrives
+    jobId.value = newJobId();
+    cancelRequested = false;
+    progress.value = emptyProgress();
 
     try {
       const uploadResult = await invoke<UploadResult>("upload_file", {
         filePath: filePath.value,
         folderId: folderId || null,
+        folderPath: folderPath || null,
+        onExisting,
+        convert,
+        ocrLanguage,
+        jobId: jobId.value,
       });
 
       result.value = uploadResult;
       status.value = "success";
     } catch (e) {
-      error.value = String(e);
-      status.value = "error";
+      // A cancelled upload rejects too, but it is not an error
+      const code = codeOf(e);
+      if (
+        code === "cancelled" ||
+        cancelRequested ||
+        status.value === "cancelled"
+      ) {
+        status.value = "cancelled";
+        return;
+      }
+      error.value = errorMessage(e);
+      errorCode.value = code;
+      status.value = code === "already_uploaded" ? "already-uploaded" : "error";
+    }
+  }
+
+  async function cancel() {
+    if (!isUploading.value || !jobId.value) return;
+    cancelRequested = true;
+    try {
+      await invoke("cancel_upload", { jobId: jobId.value });
+    } catch {
+      // The upload finished before the request arrived
+      cancelRequested = false;
     }
   }
 
@@ -110,9 +214,11 @@ export const useUploadStore = defineStore("upload", () => {
     fileType.value = null;
     fileSize.value = 0;
     status.value = "idle";
-    progress.value = { bytes_uploaded: 0, total_bytes: 0, percentage: 0 };
+    progress.value = emptyProgress();
+    jobId.value = null;
     result.value = null;
     error.value = null;
+    errorCode.value = null;
   }
 
   function cleanup() {
@@ -120,6 +226,10 @@ export const useUploadStore = defineStore("upload", () => {
       unlistenProgress();
       unlistenProgress = null;
     }
+    if (unlistenCancelled) {
+      unlistenCancelled();
+      unlistenCancelled = null;
+    }
   }
 
   return {
@@ -129,13 +239,19 @@ export const useUploadStore = defineStore("upload", () => {
     fileSize,
     status,
     progress,
+    jobId,
     result,
     error,
+    errorCode,
     isUploading,
     isComplete,
     hasError,
+    isCancelled,
+    isAlreadyUploaded,
+    progressDetail,
     setFile,
     upload,
+    cancel,
     reset,
     cleanup,
   };